@@ -0,0 +1,162 @@
+// Concurrency that adapts to observed latency, AIMD-style
+
+use tokio::task::JoinSet;
+
+use crate::{
+    agent_runtime::{check_lengths, join_failure, AgentResult, AgentStatus, RustAgent},
+    backend::ema,
+    error::{AgentError, Result},
+    telemetry,
+};
+
+// How far above its best the EMA may drift before it counts as congestion
+const LATENCY_TOLERANCE: f64 = 1.5;
+const DECREASE_FACTOR: f64 = 0.5;
+
+/// Same as [`crate::execute_parallel_limited`], but the concurrency limit
+/// adjusts itself between 1 and `max`, starting at `initial`. While the
+/// moving average of completed requests' latency stays within 1.5x of the
+/// best seen, the limit grows by about one per round of requests; once it
+/// rises past that, or a request times out, the limit is halved. This
+/// finds roughly the most concurrency the backend can take before requests
+/// start queueing. Results come back in completion order.
+pub async fn execute_parallel_adaptive(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+    initial: usize,
+    max: usize,
+) -> Result<Vec<AgentResult>> {
+    check_lengths(agents.len(), tasks.len())?;
+    if initial == 0 || initial > max {
+        return Err(AgentError::Config(format!(
+            "initial concurrency must be between 1 and max ({max}), got {initial}"
+        )));
+    }
+
+    let mut queue: Vec<_> = agents.into_iter().zip(tasks).enumerate().collect();
+    queue.sort_by_key(|(_, (agent, _))| agent.config().priority);
+    let mut queue = queue.into_iter();
+    let mut controller = Aimd::new(initial, max);
+    let mut set = JoinSet::new();
+    let mut results = Vec::with_capacity(queue.len());
+
+    loop {
+        while set.len() < controller.limit() {
+            let Some((index, (agent, task))) = queue.next() else {
+                break;
+            };
+            set.spawn(telemetry::in_current_span(async move {
+                agent.execute_settled(&task).await.for_task(index)
+            }));
+        }
+        let Some(joined) = set.join_next().await else {
+            break;
+        };
+        let result = joined.unwrap_or_else(join_failure);
+        controller.observe(&result);
+        results.push(result);
+    }
+    Ok(results)
+}
+
+// Additive-increase/multiplicative-decrease over a latency EMA
+#[derive(Debug)]
+struct Aimd {
+    limit: f64,
+    max: f64,
+    ema_ms: Option<f64>,
+    best_ms: f64,
+    // Completions to ignore after a decrease: requests started under the
+    // old limit would otherwise cut it again
+    cooldown: usize,
+}
+
+impl Aimd {
+    fn new(initial: usize, max: usize) -> Self {
+        Self {
+            limit: initial as f64,
+            max: max as f64,
+            ema_ms: None,
+            best_ms: f64::INFINITY,
+            cooldown: 0,
+        }
+    }
+
+    fn limit(&self) -> usize {
+        self.limit as usize
+    }
+
+    fn observe(&mut self, result: &AgentResult) {
+        let congested = match result.status {
+            AgentStatus::Completed => {
+                let ema = ema(self.ema_ms, result.duration_ms as f64);
+                self.ema_ms = Some(ema);
+                self.best_ms = self.best_ms.min(ema);
+                ema > self.best_ms * LATENCY_TOLERANCE
+            },
+            AgentStatus::TimedOut => true,
+            // Fast failures say nothing about load
+            AgentStatus::Failed | AgentStatus::Cancelled => return,
+        };
+
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+        } else if congested {
+            self.limit = (self.limit * DECREASE_FACTOR).max(1.0);
+            self.cooldown = self.limit();
+        } else {
+            self.limit = (self.limit + 1.0 / self.limit).min(self.max);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{mock::MockBackend, AgentConfig};
+
+    fn completed(duration_ms: u128) -> AgentResult {
+        AgentResult {
+            duration_ms,
+            ..AgentResult::completed("test", "ok")
+        }
+    }
+
+    #[test]
+    fn test_limit_ramps_while_flat_and_halves_on_rising_latency() {
+        let mut aimd = Aimd::new(2, 8);
+        for _ in 0..40 {
+            aimd.observe(&completed(100));
+        }
+        assert_eq!(aimd.limit(), 8, "flat latency ramps up to max");
+
+        for _ in 0..3 {
+            aimd.observe(&completed(400));
+        }
+        assert_eq!(aimd.limit(), 4);
+        aimd.observe(&AgentResult {
+            status: AgentStatus::TimedOut,
+            ..completed(0)
+        });
+        assert_eq!(aimd.limit(), 4, "cooling down after the cut");
+    }
+
+    #[tokio::test]
+    async fn test_execute_parallel_adaptive_runs_every_agent() {
+        let agent = RustAgent::with_backend(
+            AgentConfig::default(),
+            Arc::new(MockBackend::with_response("ok")),
+        );
+        let results = execute_parallel_adaptive(vec![agent; 5], vec!["a".to_string(); 5], 1, 3)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 5);
+        assert!(results.iter().all(|r| r.status == AgentStatus::Completed));
+
+        assert!(execute_parallel_adaptive(vec![], vec![], 0, 3)
+            .await
+            .is_err());
+    }
+}
@@ -1,110 +1,3935 @@
 // Rust-based agent runtime for performance-critical operations
 
-use std::time::Duration;
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
 
-use anyhow::Result;
+use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use tokio::{sync::Semaphore, task::JoinSet};
+use tokio_util::sync::CancellationToken;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[cfg(feature = "cache")]
+use crate::cache;
+#[cfg(feature = "metrics")]
+use crate::metrics;
+#[cfg(feature = "otel")]
+use crate::otel;
+use crate::{
+    backend::{ByteStream, HttpBackend, LlmBackend, LlmRequest, LlmResponse},
+    circuit,
+    error::{AgentError, Result},
+    middleware::Middleware,
+    models::{self, PullProgress},
+    rate_limit,
+    retry::{self, RetryPolicy},
+    streaming::{self, StreamEvent},
+    telemetry::{self, trace_event},
+    template::PromptTemplate,
+};
+
+/// The `User-Agent` of agents whose config sets none
+pub const DEFAULT_USER_AGENT: &str = concat!("self-hosted-ai-agents/", env!("CARGO_PKG_VERSION"));
+
+const OPENAI_CHAT_PATH: &str = "/v1/chat/completions";
+// Upper bound on `pull_model`, which downloads gigabytes for large models
+const PULL_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+const OPENAI_OUTPUT_POINTER: &str = "/choices/0/message/content";
+// The placeholders an `AgentConfig::request_template` may use
+const REQUEST_TEMPLATE_VARS: [&str; 3] = ["prompt", "model", "temperature"];
+// Ollama's errors for images sent to a model without vision support: older
+// servers report the missing projector, newer ones the missing capability
+const NO_VISION_ERRORS: [&str; 2] = [
+    "missing data required for image input",
+    "does not support vision",
+];
+// How much of an unparseable body `not_json` quotes
+const BODY_SNIPPET_CHARS: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentConfig {
     pub name: String,
     pub model: String,
     pub ollama_url: String,
     pub temperature: f32,
     pub timeout_seconds: u64,
+    /// Limit on establishing the TCP/TLS connection, separate from the
+    /// `timeout_seconds` total. Applied to the client built by
+    /// [`RustAgent::new`]; agents given a client use its settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_seconds: Option<u64>,
+    /// Longest gap allowed between reads of the response, e.g. while a slow
+    /// model loads; `timeout_seconds` still bounds the whole request. Applied
+    /// like `connect_timeout_seconds`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_timeout_seconds: Option<u64>,
+    /// How long an idle keep-alive connection stays pooled before it is
+    /// closed. Set it below the server's own idle timeout (or how long a
+    /// restart takes) so stale connections are evicted before reuse.
+    /// Applied like `connect_timeout_seconds`, and by
+    /// [`crate::AgentPool::from_config`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_idle_timeout_seconds: Option<u64>,
+    /// Most idle connections kept per host; zero disables connection reuse.
+    /// Applied like `pool_idle_timeout_seconds`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_max_idle_per_host: Option<usize>,
+    /// HTTP(S) proxy every request goes through, e.g.
+    /// `http://proxy.corp:3128`. Applied like `connect_timeout_seconds`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_url: Option<String>,
+    /// Comma-separated hosts, domains, or CIDRs that bypass `proxy_url`, in
+    /// the `NO_PROXY` format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+    /// PEM-encoded CA certificate(s) to trust in addition to the system
+    /// roots, for gateways signed by a private CA. Applied like
+    /// `connect_timeout_seconds`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_root_cert_pem: Option<String>,
+    /// DANGEROUS: skip TLS certificate verification entirely, so any server,
+    /// including an attacker in the middle, is accepted. Only for testing
+    /// against self-signed certificates; prefer `tls_root_cert_pem`.
+    #[serde(default)]
+    pub tls_accept_invalid_certs: bool,
+    /// `User-Agent` sent on every request, so gateway and Ollama logs can tell
+    /// this runtime's traffic apart; unset means [`DEFAULT_USER_AGENT`].
+    /// Applied like `connect_timeout_seconds`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Retries after the first attempt for the failures `retry_policy`
+    /// allows. Zero disables retrying.
+    #[serde(default)]
+    pub max_retries: u32,
+    /// Which failures are retried; by default transport failures and 5xx
+    /// responses. A `Custom` policy is left out when serializing.
+    #[serde(default, skip_serializing_if = "RetryPolicy::is_custom")]
+    pub retry_policy: RetryPolicy,
+    /// Base delay before the first retry, doubled on each subsequent attempt.
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    /// Randomize each retry delay between zero and the computed backoff so
+    /// agents that failed together spread out their retries.
+    #[serde(default)]
+    pub retry_jitter: bool,
+    #[serde(default)]
+    pub options: GenerationOptions,
+    /// System prompt sent with every request; omitted from the body when
+    /// `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    /// How long Ollama keeps the model loaded after a request, e.g. `"30m"`;
+    /// `"-1"` keeps it loaded indefinitely. Uses the server default when
+    /// `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<String>,
+    #[serde(default)]
+    pub format: OutputFormat,
+    /// Mark the result `Failed` when the generated output is not valid JSON.
+    #[serde(default)]
+    pub validate_json: bool,
+    /// When the output holds exactly one ```` ``` ```` fenced block, keep only
+    /// the code inside it, dropping the fences and any chatter around them.
+    #[serde(default)]
+    pub strip_markdown_fences: bool,
+    /// Trim leading and trailing whitespace from the output, after
+    /// `strip_markdown_fences`.
+    #[serde(default)]
+    pub trim_output: bool,
+    /// When the model isn't installed (Ollama answers 404), pull it with
+    /// `/api/pull` and retry the request once. Ollama-native servers only.
+    /// The pull may take up to an hour regardless of `timeout_seconds`, and
+    /// the retried request gets a full `timeout_seconds` of its own.
+    #[serde(default)]
+    pub auto_pull: bool,
+    /// Consecutive backend failures (transport errors, timeouts, 5xx) after
+    /// which requests to this `ollama_url` fail fast with `"circuit open"`.
+    /// Zero disables the circuit breaker.
+    #[serde(default)]
+    pub failure_threshold: u32,
+    /// How long an open circuit rejects requests before letting a probe
+    /// through. At most one day.
+    #[serde(default = "default_cooldown_ms")]
+    pub cooldown_ms: u64,
+    /// Extra HTTP headers sent with every request, e.g. for a reverse proxy
+    /// in front of Ollama.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// Sent as `Authorization: Bearer <token>`; takes precedence over an
+    /// `Authorization` entry in `headers`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer_token: Option<String>,
+    /// Model to retry with, once, when the server reports `model` as missing
+    /// or unable to load. Timeouts and network errors never fall back. The
+    /// fallback request gets a full `timeout_seconds` of its own, so an
+    /// execution that falls back may take up to twice as long.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_model: Option<String>,
+    /// Upper bound on request starts per second against this `ollama_url`,
+    /// shared by every agent targeting it. Retries count as requests. At
+    /// least one request a day; unlimited when `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_second: Option<f64>,
+    #[serde(default)]
+    pub api_flavor: ApiFlavor,
+    /// Maximum number of completed results kept in the process-wide response
+    /// cache (feature `cache`); identical requests to the same server with
+    /// the same credentials are then answered from it. Zero disables caching.
+    /// The cache is sized by the first agent that uses it: a different
+    /// non-zero capacity on a later agent still turns caching on for that
+    /// agent but does not resize the shared cache. Agents with [`Middleware`]
+    /// installed bypass the cache, since it may rewrite the request after any
+    /// key is taken.
+    #[serde(default)]
+    pub cache_capacity: usize,
+    /// How long a cached result stays valid. Cached entries never expire when
+    /// `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_ttl_seconds: Option<u64>,
+    #[serde(default)]
+    pub priority: Priority,
+    /// Longest task (in characters) `execute` will send; what happens to a
+    /// longer one is up to `chunk_strategy`. Unlimited when `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_prompt_chars: Option<usize>,
+    #[serde(default)]
+    pub chunk_strategy: ChunkStrategy,
+    /// Largest reply body (in bytes) to read before giving up with a
+    /// `Failed` "response too large" result, counted across the whole stream
+    /// when streaming. Guards against runaway or hostile backends; unlimited
+    /// when `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_response_bytes: Option<usize>,
+    /// Where `execute` finds the generated text in the reply, as dotted keys
+    /// with optional array indices, e.g. `message.content` or
+    /// `choices[0].text`. Defaults to `response`, or
+    /// `choices[0].message.content` for [`ApiFlavor::OpenAiCompatible`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_path: Option<String>,
+    /// The JSON body `execute` sends in place of the flavor's own, for
+    /// gateways with a non-standard request shape. `{prompt}`, `{model}`, and
+    /// `{temperature}` are substituted in every string value; a string that is
+    /// exactly `"{temperature}"` becomes a number. `{prompt}` is required and
+    /// no other placeholder is allowed. The request still goes to the
+    /// `api_flavor`'s path; pair it with `response_path` to read the reply.
+    /// `chat` and streaming are unaffected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_template: Option<serde_json::Value>,
+    /// Opaque caller data, e.g. a user or trace id, copied into every
+    /// [`AgentResult::metadata`]. Never sent to the server.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+}
+
+/// What `execute` does with a task longer than
+/// [`AgentConfig::max_prompt_chars`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkStrategy {
+    /// Return a `Failed` result with a "prompt too long" error
+    #[default]
+    Fail,
+    /// Run each `max_prompt_chars`-sized piece in turn and join the outputs
+    /// with newlines. Stops at the first piece that doesn't complete; the
+    /// timeout applies to each piece separately.
+    Split,
+}
+
+/// Start order under a concurrency cap: when
+/// [`execute_parallel_limited`] has more agents than permits, higher
+/// priorities are started first. Has no effect without contention.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+/// Which request/response shape `execute` and `chat` speak. Streaming,
+/// embeddings, and image inputs always use the Ollama-native API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiFlavor {
+    /// Ollama's `/api/generate` and `/api/chat`
+    #[default]
+    OllamaNative,
+    /// `/v1/chat/completions`, as served by OpenAI, vLLM, or Ollama's
+    /// compatibility layer
+    OpenAiCompatible,
+}
+
+/// Shape Ollama should constrain the generated output to.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// Free-form text; no `format` field is sent
+    #[default]
+    Text,
+    /// `"format": "json"`
+    Json,
+    /// A JSON schema sent as `format`; requires an Ollama version with
+    /// structured output support
+    JsonSchema(serde_json::Value),
+}
+
+impl AgentConfig {
+    pub fn builder() -> AgentConfigBuilder {
+        AgentConfigBuilder::default()
+    }
+
+    /// Checks the invariants enforced by [`AgentConfigBuilder::build`].
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(AgentError::Config(
+                "agent config: name must not be empty".to_string(),
+            ));
+        }
+        if self.model.trim().is_empty() {
+            return Err(self.invalid("model must not be empty"));
+        }
+        if self.ollama_url.trim().is_empty() {
+            return Err(self.invalid("ollama_url must not be empty"));
+        }
+        if !(0.0..=2.0).contains(&self.temperature) {
+            return Err(self.invalid(format!(
+                "temperature must be within 0.0..=2.0, got {}",
+                self.temperature
+            )));
+        }
+        if self.timeout_seconds == 0 {
+            return Err(self.invalid("timeout_seconds must be greater than 0"));
+        }
+        if self.connect_timeout_seconds == Some(0) {
+            return Err(self.invalid("connect_timeout_seconds must be greater than 0"));
+        }
+        if self.read_timeout_seconds == Some(0) {
+            return Err(self.invalid("read_timeout_seconds must be greater than 0"));
+        }
+        if self.pool_idle_timeout_seconds == Some(0) {
+            return Err(self.invalid("pool_idle_timeout_seconds must be greater than 0"));
+        }
+        if Duration::from_millis(self.cooldown_ms) > circuit::MAX_COOLDOWN {
+            return Err(self.invalid(format!(
+                "cooldown_ms must be at most {} (one day), got {}",
+                circuit::MAX_COOLDOWN.as_millis(),
+                self.cooldown_ms
+            )));
+        }
+        if let Some(rate) = self.requests_per_second {
+            if !rate_limit::is_valid_rate(rate) {
+                return Err(self.invalid(format!(
+                    "requests_per_second must be at least {} (one request a day), got {rate}",
+                    rate_limit::MIN_REQUESTS_PER_SECOND
+                )));
+            }
+        }
+        if let Some(proxy_url) = &self.proxy_url {
+            reqwest::Proxy::all(proxy_url)
+                .map_err(|e| self.invalid(format!("invalid proxy_url '{proxy_url}': {e}")))?;
+        }
+        if let Some(path) = &self.response_path {
+            response_pointer(path).map_err(|problem| self.invalid(problem))?;
+        }
+        self.validate_request_template()?;
+        if self.max_prompt_chars == Some(0) {
+            return Err(self.invalid("max_prompt_chars must be greater than 0"));
+        }
+        if self.max_response_bytes == Some(0) {
+            return Err(self.invalid("max_response_bytes must be greater than 0"));
+        }
+        self.validate_headers()
+    }
+
+    // Every placeholder in a request template must be one `build_request`
+    // fills, and the prompt must go somewhere. Also checked by the Python
+    // bindings, which take the template as JSON text.
+    pub(crate) fn validate_request_template(&self) -> Result<()> {
+        let Some(template) = &self.request_template else {
+            return Ok(());
+        };
+        let mut placeholders = Vec::new();
+        template_strings(template, &mut |text| {
+            for name in PromptTemplate::new(text).placeholders() {
+                if !placeholders.iter().any(|seen| seen == name) {
+                    placeholders.push(name.to_string());
+                }
+            }
+        });
+
+        if let Some(unknown) = placeholders
+            .iter()
+            .find(|name| !REQUEST_TEMPLATE_VARS.contains(&name.as_str()))
+        {
+            return Err(self.invalid(format!(
+                "request_template has unknown placeholder '{{{unknown}}}'"
+            )));
+        }
+        if !placeholders.iter().any(|name| name == "prompt") {
+            return Err(self.invalid("request_template must contain '{prompt}'"));
+        }
+        Ok(())
+    }
+
+    // Header names and values must be sendable as-is; reqwest would
+    // otherwise only fail at request time.
+    pub(crate) fn validate_headers(&self) -> Result<()> {
+        for (name, value) in &self.headers {
+            if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(self.invalid(format!("invalid header name '{name}'")));
+            }
+            if reqwest::header::HeaderValue::from_str(value).is_err() {
+                return Err(self.invalid(format!("invalid value for header '{name}'")));
+            }
+        }
+        if let Some(user_agent) = &self.user_agent {
+            if reqwest::header::HeaderValue::from_str(user_agent).is_err() {
+                return Err(self.invalid(format!("invalid user_agent '{user_agent}'")));
+            }
+        }
+        if let Some(token) = &self.bearer_token {
+            if reqwest::header::HeaderValue::from_str(&format!("Bearer {token}")).is_err() {
+                return Err(self.invalid("invalid bearer_token"));
+            }
+        }
+        Ok(())
+    }
+
+    /// A stable hash of everything in this config that shapes the request
+    /// `execute` sends for `prompt`: server, API flavor, model, temperature,
+    /// options, system prompt, output format, keep-alive, request template,
+    /// and the headers sent with it, including `user_agent` and a digest of
+    /// `bearer_token`. Fields that never reach the request, such as `name`,
+    /// `metadata`, timeouts, and retry settings, are left out, so two configs
+    /// with the same fingerprint for a prompt send the same request, with the
+    /// same credentials, to the same server. It does not cover settings that
+    /// only shape the result, e.g. `response_path`. The hash does not depend
+    /// on the process, so it can key persistent caches; the response cache
+    /// uses it too.
+    pub fn fingerprint(&self, prompt: &str) -> u64 {
+        let mut key = serde_json::json!({
+            "ollama_url": self.ollama_url,
+            "api_flavor": self.api_flavor,
+            "model": self.model,
+            "temperature": self.temperature,
+            "options": self.options,
+            "system": self.system,
+            "format": self.format,
+            "keep_alive": self.keep_alive,
+            "prompt": prompt,
+        });
+        // Only when set, so configs without one keep their earlier hashes
+        if let Some(template) = &self.request_template {
+            key["request_template"] = template.clone();
+        }
+        if !self.headers.is_empty() {
+            key["headers"] = serde_json::json!(self.headers);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            key["user_agent"] = serde_json::json!(user_agent);
+        }
+        // A digest, so the token itself never ends up in the hashed text
+        if let Some(token) = &self.bearer_token {
+            key["bearer_token"] = serde_json::json!(fnv1a(token.as_bytes()));
+        }
+        // Over the canonical (key-sorted) JSON
+        fnv1a(key.to_string().as_bytes())
+    }
+
+    // `fingerprint` plus every setting that changes what the response turns
+    // into, so equal values mean the same result and not just the same
+    // request
+    pub(crate) fn result_fingerprint(&self, prompt: &str) -> u64 {
+        let key = serde_json::json!({
+            "request": self.fingerprint(prompt),
+            "response_path": self.response_path,
+            "strip_markdown_fences": self.strip_markdown_fences,
+            "trim_output": self.trim_output,
+            "validate_json": self.validate_json,
+            "max_prompt_chars": self.max_prompt_chars,
+            "chunk_strategy": self.chunk_strategy,
+            "max_response_bytes": self.max_response_bytes,
+            "fallback_model": self.fallback_model,
+            "auto_pull": self.auto_pull,
+        });
+        fnv1a(key.to_string().as_bytes())
+    }
+
+    fn invalid(&self, problem: impl std::fmt::Display) -> AgentError {
+        AgentError::Config(format!("agent config '{}': {problem}", self.name))
+    }
+}
+
+/// Fluent constructor for [`AgentConfig`]. Unset fields take the values from
+/// `AgentConfig::default()`; `build` rejects configurations that would only
+/// fail (or silently misbehave) at request time.
+#[derive(Debug, Default)]
+pub struct AgentConfigBuilder {
+    config: AgentConfig,
+}
+
+impl AgentConfigBuilder {
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.config.name = name.into();
+        self
+    }
+
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.config.model = model.into();
+        self
+    }
+
+    pub fn ollama_url(mut self, url: impl Into<String>) -> Self {
+        self.config.ollama_url = url.into();
+        self
+    }
+
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.config.temperature = temperature;
+        self
+    }
+
+    pub fn timeout_seconds(mut self, seconds: u64) -> Self {
+        self.config.timeout_seconds = seconds;
+        self
+    }
+
+    pub fn connect_timeout_seconds(mut self, seconds: u64) -> Self {
+        self.config.connect_timeout_seconds = Some(seconds);
+        self
+    }
+
+    pub fn read_timeout_seconds(mut self, seconds: u64) -> Self {
+        self.config.read_timeout_seconds = Some(seconds);
+        self
+    }
+
+    pub fn pool_idle_timeout_seconds(mut self, seconds: u64) -> Self {
+        self.config.pool_idle_timeout_seconds = Some(seconds);
+        self
+    }
+
+    pub fn pool_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.config.pool_max_idle_per_host = Some(max_idle);
+        self
+    }
+
+    pub fn proxy(mut self, proxy_url: impl Into<String>, no_proxy: Option<String>) -> Self {
+        self.config.proxy_url = Some(proxy_url.into());
+        self.config.no_proxy = no_proxy;
+        self
+    }
+
+    pub fn tls_root_cert_pem(mut self, pem: impl Into<String>) -> Self {
+        self.config.tls_root_cert_pem = Some(pem.into());
+        self
+    }
+
+    /// See [`AgentConfig::tls_accept_invalid_certs`]; never enable this in
+    /// production.
+    pub fn tls_danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.config.tls_accept_invalid_certs = accept;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.config.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.config.max_retries = retries;
+        self
+    }
+
+    pub fn retry_backoff_ms(mut self, backoff_ms: u64) -> Self {
+        self.config.retry_backoff_ms = backoff_ms;
+        self
+    }
+
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.config.retry_policy = policy;
+        self
+    }
+
+    pub fn retry_jitter(mut self, jitter: bool) -> Self {
+        self.config.retry_jitter = jitter;
+        self
+    }
+
+    pub fn options(mut self, options: GenerationOptions) -> Self {
+        self.config.options = options;
+        self
+    }
+
+    pub fn system(mut self, system: impl Into<String>) -> Self {
+        self.config.system = Some(system.into());
+        self
+    }
+
+    pub fn keep_alive(mut self, keep_alive: impl Into<String>) -> Self {
+        self.config.keep_alive = Some(keep_alive.into());
+        self
+    }
+
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.config.format = format;
+        self
+    }
+
+    pub fn auto_pull(mut self, auto_pull: bool) -> Self {
+        self.config.auto_pull = auto_pull;
+        self
+    }
+
+    pub fn validate_json(mut self, validate: bool) -> Self {
+        self.config.validate_json = validate;
+        self
+    }
+
+    pub fn strip_markdown_fences(mut self, strip: bool) -> Self {
+        self.config.strip_markdown_fences = strip;
+        self
+    }
+
+    pub fn trim_output(mut self, trim: bool) -> Self {
+        self.config.trim_output = trim;
+        self
+    }
+
+    pub fn failure_threshold(mut self, threshold: u32) -> Self {
+        self.config.failure_threshold = threshold;
+        self
+    }
+
+    pub fn cooldown_ms(mut self, cooldown_ms: u64) -> Self {
+        self.config.cooldown_ms = cooldown_ms;
+        self
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.headers.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.config.bearer_token = Some(token.into());
+        self
+    }
+
+    pub fn fallback_model(mut self, model: impl Into<String>) -> Self {
+        self.config.fallback_model = Some(model.into());
+        self
+    }
+
+    pub fn requests_per_second(mut self, rate: f64) -> Self {
+        self.config.requests_per_second = Some(rate);
+        self
+    }
+
+    pub fn api_flavor(mut self, flavor: ApiFlavor) -> Self {
+        self.config.api_flavor = flavor;
+        self
+    }
+
+    pub fn max_prompt_chars(mut self, max_chars: usize, strategy: ChunkStrategy) -> Self {
+        self.config.max_prompt_chars = Some(max_chars);
+        self.config.chunk_strategy = strategy;
+        self
+    }
+
+    pub fn max_response_bytes(mut self, max_bytes: usize) -> Self {
+        self.config.max_response_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn response_path(mut self, path: impl Into<String>) -> Self {
+        self.config.response_path = Some(path.into());
+        self
+    }
+
+    pub fn request_template(mut self, template: serde_json::Value) -> Self {
+        self.config.request_template = Some(template);
+        self
+    }
+
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.config.priority = priority;
+        self
+    }
+
+    pub fn cache(mut self, capacity: usize, ttl_seconds: Option<u64>) -> Self {
+        self.config.cache_capacity = capacity;
+        self.config.cache_ttl_seconds = ttl_seconds;
+        self
+    }
+
+    pub fn build(self) -> Result<AgentConfig> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+/// Sampling parameters sent under Ollama's `options` object. Unset fields are
+/// omitted from the request so the model's Modelfile defaults apply.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// Maximum tokens to generate; `-1` means unlimited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i32>,
+    /// Fixed seed for reproducible sampling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f32>,
+    /// Context window size in tokens, overriding the Modelfile's for this
+    /// request. Larger windows fit longer prompts; smaller ones save memory
+    /// when many small agents share a GPU.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    /// Sequences that end generation when produced; omitted when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    /// Send the prompt verbatim with `"raw": true`, bypassing the Modelfile's
+    /// prompt template. The caller is then responsible for the model's exact
+    /// prompt format, special tokens included. Only `/api/generate` honours
+    /// it; this travels beside `options` in the request, not inside it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub raw: bool,
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    500
+}
+
+fn default_cooldown_ms() -> u64 {
+    30_000
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            model: String::new(),
+            ollama_url: "http://localhost:11434".to_string(),
+            temperature: 0.7,
+            timeout_seconds: 60,
+            max_retries: 0,
+            retry_policy: RetryPolicy::OnServerError,
+            retry_backoff_ms: default_retry_backoff_ms(),
+            retry_jitter: false,
+            options: GenerationOptions::default(),
+            system: None,
+            keep_alive: None,
+            format: OutputFormat::Text,
+            validate_json: false,
+            strip_markdown_fences: false,
+            trim_output: false,
+            auto_pull: false,
+            failure_threshold: 0,
+            cooldown_ms: default_cooldown_ms(),
+            headers: HashMap::new(),
+            bearer_token: None,
+            fallback_model: None,
+            requests_per_second: None,
+            api_flavor: ApiFlavor::OllamaNative,
+            cache_capacity: 0,
+            cache_ttl_seconds: None,
+            priority: Priority::Normal,
+            max_prompt_chars: None,
+            chunk_strategy: ChunkStrategy::Fail,
+            max_response_bytes: None,
+            connect_timeout_seconds: None,
+            read_timeout_seconds: None,
+            pool_idle_timeout_seconds: None,
+            pool_max_idle_per_host: None,
+            response_path: None,
+            request_template: None,
+            metadata: HashMap::new(),
+            proxy_url: None,
+            no_proxy: None,
+            tls_root_cert_pem: None,
+            tls_accept_invalid_certs: false,
+            user_agent: None,
+        }
+    }
+}
+
+/// Outcome of a single agent execution. Serializes to the lowercase strings
+/// (`"completed"`, `"failed"`, `"timed_out"`, `"cancelled"`) used before this
+/// was typed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentStatus {
+    Completed,
+    Failed,
+    TimedOut,
+    Cancelled,
+}
+
+impl AgentStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AgentStatus::Completed => "completed",
+            AgentStatus::Failed => "failed",
+            AgentStatus::TimedOut => "timed_out",
+            AgentStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl std::fmt::Display for AgentStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentResult {
+    /// The `name` of the agent's config
     pub agent_id: String,
-    pub status: String,
+    /// Unique to this execution
+    pub run_id: uuid::Uuid,
+    pub status: AgentStatus,
     pub output: Option<String>,
     pub error: Option<String>,
     pub duration_ms: u128,
+    /// Ollama's `prompt_eval_count`
+    #[serde(default)]
+    pub prompt_tokens: Option<u64>,
+    /// Ollama's `eval_count`
+    #[serde(default)]
+    pub completion_tokens: Option<u64>,
+    /// Ollama's `eval_duration`, converted from nanoseconds
+    #[serde(default)]
+    pub eval_duration_ms: Option<u64>,
+    /// The primary model was unavailable and `fallback_model` produced this
+    /// result.
+    #[serde(default)]
+    pub used_fallback: bool,
+    /// The config's `metadata`, unchanged
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+    /// Time from sending the request to the first non-empty chunk; only set
+    /// by [`RustAgent::execute_streamed`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_token_ms: Option<u64>,
+    /// Position of this result's task in the inputs of the batch that ran
+    /// it, for matching results run in completion order back to their
+    /// inputs; `None` outside a batch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_index: Option<usize>,
+    /// Layout of the serialized result; always [`AgentResult::SCHEMA_VERSION`]
+    /// for results produced by this version. Records written before it was
+    /// added read as `1`, and [`crate::read_results`] upgrades them.
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32,
+}
+
+fn legacy_schema_version() -> u32 {
+    1
+}
+
+impl AgentResult {
+    /// The current [`AgentResult::schema_version`]. Version 1 is the original
+    /// `agent_id`, `status`, `output`, `error`, and `duration_ms`; version 2
+    /// added `run_id`, token counts, `used_fallback`, `metadata`,
+    /// `first_token_ms`, and `task_index`.
+    pub const SCHEMA_VERSION: u32 = 2;
+
+    // Records which input of a batch this result answers
+    pub(crate) fn for_task(self, index: usize) -> Self {
+        Self {
+            task_index: Some(index),
+            ..self
+        }
+    }
+
+    // A completed result for tests that only care about a few fields
+    #[cfg(test)]
+    pub(crate) fn completed(agent_id: &str, output: &str) -> Self {
+        Self {
+            agent_id: agent_id.to_string(),
+            run_id: uuid::Uuid::new_v4(),
+            status: AgentStatus::Completed,
+            output: Some(output.to_string()),
+            error: None,
+            duration_ms: 1,
+            prompt_tokens: None,
+            completion_tokens: None,
+            eval_duration_ms: None,
+            used_fallback: false,
+            metadata: HashMap::new(),
+            first_token_ms: None,
+            task_index: None,
+            schema_version: Self::SCHEMA_VERSION,
+        }
+    }
+
+    // Merges the result of the next chunk of a split task into this one
+    fn followed_by(self, next: AgentResult) -> AgentResult {
+        fn sum(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+            match (a, b) {
+                (None, None) => None,
+                (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+            }
+        }
+
+        let output = match (self.output, next.output) {
+            (Some(a), Some(b)) => Some(format!("{a}\n{b}")),
+            (a, b) => a.or(b),
+        };
+        AgentResult {
+            output,
+            duration_ms: self.duration_ms + next.duration_ms,
+            prompt_tokens: sum(self.prompt_tokens, next.prompt_tokens),
+            completion_tokens: sum(self.completion_tokens, next.completion_tokens),
+            eval_duration_ms: sum(self.eval_duration_ms, next.eval_duration_ms),
+            used_fallback: self.used_fallback || next.used_fallback,
+            status: next.status,
+            error: next.error,
+            ..self
+        }
+    }
+}
+
+/// A single turn in an `/api/chat` conversation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    /// `system`, `user`, or `assistant`
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: content.into(),
+        }
+    }
 }
 
+#[derive(Clone)]
 pub struct RustAgent {
     config: AgentConfig,
-    client: reqwest::Client,
+    backend: Arc<dyn LlmBackend>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    // Shared with the rest of a batch by `execute_parallel_with_retry_budget`
+    retry_budget: Option<Arc<retry::RetryBudget>>,
 }
 
 impl RustAgent {
+    // Timeouts are enforced per request rather than on the client, so they
+    // can be overridden per call.
     pub fn new(config: AgentConfig) -> Result<Self> {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(config.timeout_seconds))
-            .build()?;
+        let client = build_client(&config)?;
+        Ok(Self::with_client(config, client))
+    }
+
+    /// Builds an agent around an existing client so several agents can share
+    /// one connection pool. The per-agent `timeout_seconds` is still applied
+    /// to each request, but the connection-level settings that
+    /// [`RustAgent::new`] builds into a client (`connect_timeout_seconds`,
+    /// `read_timeout_seconds`, pool tuning, `proxy_url`, `no_proxy`, `tls_*`,
+    /// and `user_agent`) are ignored in favour of `client`'s own.
+    pub fn with_client(config: AgentConfig, client: reqwest::Client) -> Self {
+        Self::with_backend(config, Arc::new(HttpBackend::new(client)))
+    }
+
+    /// Builds an agent that sends its requests through `backend`, e.g. a
+    /// [`crate::mock::MockBackend`] in tests.
+    pub fn with_backend(config: AgentConfig, backend: Arc<dyn LlmBackend>) -> Self {
+        Self {
+            config,
+            backend,
+            middleware: Vec::new(),
+            retry_budget: None,
+        }
+    }
+
+    /// Adds `middleware` after any already installed; see [`Middleware`].
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    pub fn config(&self) -> &AgentConfig {
+        &self.config
+    }
 
-        Ok(Self { config, client })
+    // Same agent and client, pointed at a different Ollama instance
+    pub(crate) fn retargeted(&self, ollama_url: &str) -> Self {
+        let mut agent = self.clone();
+        agent.config.ollama_url = ollama_url.to_string();
+        agent
     }
 
     pub async fn execute(&self, task: &str) -> Result<AgentResult> {
-        let start = std::time::Instant::now();
+        self.execute_with_timeout(task, self.timeout()).await
+    }
 
-        // Call Ollama API
-        let response = self
-            .client
-            .post(format!("{}/api/generate", self.config.ollama_url))
-            .json(&serde_json::json!({
-                "model": self.config.model,
-                "prompt": task,
-                "temperature": self.config.temperature,
-                "stream": false,
-            }))
-            .send()
+    /// Like [`RustAgent::execute`], but bounds the whole execution, retries
+    /// included, by `timeout` instead of `config.timeout_seconds`. Exceeding
+    /// it yields a `TimedOut` result.
+    pub async fn execute_with_timeout(&self, task: &str, timeout: Duration) -> Result<AgentResult> {
+        let Some(max_chars) = self.config.max_prompt_chars else {
+            return self.execute_once(task, timeout).await;
+        };
+        let chars = task.chars().count();
+        if chars <= max_chars {
+            return self.execute_once(task, timeout).await;
+        }
+
+        match self.config.chunk_strategy {
+            ChunkStrategy::Fail => Ok(self.unfinished(
+                AgentStatus::Failed,
+                format!("prompt too long: {chars} characters, max_prompt_chars is {max_chars}"),
+                0,
+            )),
+            ChunkStrategy::Split => {
+                let mut combined: Option<AgentResult> = None;
+                for chunk in split_chars(task, max_chars) {
+                    let result = self.execute_once(chunk, timeout).await?;
+                    let completed = result.status == AgentStatus::Completed;
+                    combined = Some(match combined {
+                        Some(previous) => previous.followed_by(result),
+                        None => result,
+                    });
+                    if !completed {
+                        break;
+                    }
+                }
+                Ok(combined.expect("an over-long task has at least one chunk"))
+            },
+        }
+    }
+
+    async fn execute_once(&self, task: &str, timeout: Duration) -> Result<AgentResult> {
+        let (result, _) = self.execute_once_with_body(task, timeout).await?;
+        Ok(result)
+    }
+
+    /// Like [`RustAgent::execute`], but also returns the untouched JSON body
+    /// of the reply, for fields `AgentResult` doesn't model such as
+    /// `context` or `total_duration`. The task is always sent as a single
+    /// request, whatever `max_prompt_chars` says. The body is `Null` when no
+    /// reply was decoded, e.g. on a timeout or a cache hit. The body's text
+    /// is exactly what the model sent, before `strip_markdown_fences` and
+    /// `trim_output` clean up the result's `output`.
+    pub async fn execute_raw(&self, task: &str) -> Result<(AgentResult, serde_json::Value)> {
+        let (result, raw) = self.execute_once_with_body(task, self.timeout()).await?;
+        Ok((result, raw.unwrap_or_default()))
+    }
+
+    async fn execute_once_with_body(
+        &self,
+        task: &str,
+        timeout: Duration,
+    ) -> Result<(AgentResult, Option<serde_json::Value>)> {
+        let body = self.build_request(task);
+        let (path, default_pointer) = match self.config.api_flavor {
+            // Call Ollama API
+            ApiFlavor::OllamaNative => ("/api/generate", "/response"),
+            ApiFlavor::OpenAiCompatible => (OPENAI_CHAT_PATH, OPENAI_OUTPUT_POINTER),
+        };
+        let pointer = match &self.config.response_path {
+            Some(response_path) => {
+                response_pointer(response_path).map_err(|problem| self.config.invalid(problem))?
+            },
+            None => default_pointer.to_string(),
+        };
+        self.run_with_body(path, &body, &pointer, timeout).await
+    }
+
+    /// The JSON body [`RustAgent::execute`] would POST for `task`, without
+    /// sending it. Useful for inspecting or snapshotting how config fields
+    /// serialize.
+    pub fn build_request(&self, task: &str) -> serde_json::Value {
+        if let Some(template) = &self.config.request_template {
+            return self.fill_request_template(template, task);
+        }
+        match self.config.api_flavor {
+            ApiFlavor::OllamaNative => self.generate_body(task, false),
+            ApiFlavor::OpenAiCompatible => self.openai_body(&[ChatMessage::new("user", task)]),
+        }
+    }
+
+    /// Runs [`RustAgent::execute`] to completion on a private single-threaded
+    /// runtime, for callers that are not async. Errors if called from inside
+    /// a tokio runtime, where blocking the thread would stall other tasks;
+    /// use `execute` there instead.
+    #[cfg(feature = "blocking")]
+    pub fn execute_blocking(&self, task: &str) -> Result<AgentResult> {
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return Err(AgentError::Config(
+                "execute_blocking called from within an async runtime; use execute().await instead"
+                    .to_string(),
+            ));
+        }
+
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| AgentError::Config(format!("failed to start runtime: {e}")))?
+            .block_on(self.execute(task))
+    }
+
+    /// Like [`RustAgent::execute`], but as an OpenTelemetry span that
+    /// continues the caller's trace. `traceparent` is the W3C header of the
+    /// incoming request; the outgoing request carries a `traceparent` for the
+    /// new span, so the Ollama call shows up inline in the distributed trace.
+    #[cfg(feature = "otel")]
+    pub async fn execute_traced(&self, task: &str, traceparent: &str) -> Result<AgentResult> {
+        use opentelemetry::trace::Span;
+
+        let parent =
+            otel::parse_traceparent(traceparent).map_err(|problem| self.config.invalid(problem))?;
+        let mut span = otel::start_span(&parent, &self.config);
+
+        let mut agent = self.clone();
+        let headers = &mut agent.config.headers;
+        headers.retain(|name, _| !name.eq_ignore_ascii_case(otel::TRACEPARENT));
+        headers.insert(
+            otel::TRACEPARENT.to_string(),
+            otel::traceparent(span.span_context()),
+        );
+
+        let outcome = agent.execute(task).await;
+        otel::end_span(&mut span, outcome.as_ref());
+        outcome
+    }
+
+    /// Like [`RustAgent::execute`], with `images` attached for a vision model
+    /// such as `llava`. Each image is sent base64-encoded in the `images`
+    /// array. Fails if no images are given or any is empty, and with
+    /// [`AgentError::Backend`] if the model rejects image input.
+    pub async fn execute_with_images(
+        &self,
+        task: &str,
+        images: Vec<Vec<u8>>,
+    ) -> Result<AgentResult> {
+        use base64::Engine;
+
+        if images.is_empty() {
+            return Err(AgentError::Config(
+                "execute_with_images requires at least one image".to_string(),
+            ));
+        }
+        if let Some(index) = images.iter().position(|image| image.is_empty()) {
+            return Err(AgentError::Config(format!("image {index} is empty")));
+        }
+
+        let mut body = self.generate_body(task, false);
+        body["images"] = images
+            .iter()
+            .map(|image| base64::engine::general_purpose::STANDARD.encode(image))
+            .collect();
+
+        let result = self
+            .run("/api/generate", &body, "/response", self.timeout())
             .await?;
+        match &result.error {
+            Some(error)
+                if result.status == AgentStatus::Failed
+                    && error.starts_with("HTTP ")
+                    && NO_VISION_ERRORS
+                        .iter()
+                        .any(|message| error.contains(message)) =>
+            {
+                Err(AgentError::Backend(format!(
+                    "model '{}' does not support image input: {error}",
+                    self.config.model
+                )))
+            },
+            _ => Ok(result),
+        }
+    }
 
-        let result: serde_json::Value = response.json().await?;
-        let duration = start.elapsed().as_millis();
+    /// Executes `task` with `temperature` forced to 0 and sampling seeded
+    /// with `seed`, for snapshot tests that expect the same output every run.
+    /// Reproducibility is only as good as the server's: Ollama honours the
+    /// seed, but GPU nondeterminism or a different model build can still
+    /// change the output.
+    pub async fn execute_reproducible(&self, task: &str, seed: u64) -> Result<AgentResult> {
+        let seed = i64::try_from(seed)
+            .map_err(|_| AgentError::Config(format!("seed {seed} does not fit in an i64")))?;
+        let mut agent = self.clone();
+        agent.config.temperature = 0.0;
+        agent.config.options.seed = Some(seed);
+        agent.execute(task).await
+    }
 
-        Ok(AgentResult {
-            agent_id: uuid::Uuid::new_v4().to_string(),
-            status: "completed".to_string(),
-            output: result
-                .get("response")
-                .and_then(|v| v.as_str())
-                .map(String::from),
-            error: None,
-            duration_ms: duration,
-        })
+    /// Renders `template` with `vars` and executes the result. Unresolved
+    /// placeholders fail before any request is sent.
+    pub async fn execute_template(
+        &self,
+        template: &PromptTemplate,
+        vars: &HashMap<String, String>,
+    ) -> Result<AgentResult> {
+        self.execute(&template.render(vars)?).await
     }
-}
 
-// Parallel processing for multiple agents
-pub async fn execute_parallel(
-    agents: Vec<RustAgent>,
-    tasks: Vec<String>,
-) -> Result<Vec<AgentResult>> {
-    use tokio::task::JoinSet;
+    /// Sends the full message history to `/api/chat`. The assistant reply
+    /// (`message.content`) becomes the result's `output`.
+    pub async fn chat(&self, messages: &[ChatMessage]) -> Result<AgentResult> {
+        match self.config.api_flavor {
+            ApiFlavor::OllamaNative => {
+                self.run(
+                    "/api/chat",
+                    &self.chat_body(messages, false),
+                    "/message/content",
+                    self.timeout(),
+                )
+                .await
+            },
+            ApiFlavor::OpenAiCompatible => self.run_openai(messages, self.timeout()).await,
+        }
+    }
 
-    let mut set = JoinSet::new();
+    async fn run_openai(&self, messages: &[ChatMessage], timeout: Duration) -> Result<AgentResult> {
+        self.run(
+            OPENAI_CHAT_PATH,
+            &self.openai_body(messages),
+            OPENAI_OUTPUT_POINTER,
+            timeout,
+        )
+        .await
+    }
 
-    for (agent, task) in agents.into_iter().zip(tasks) {
-        set.spawn(async move { agent.execute(&task).await });
+    async fn run(
+        &self,
+        path: &str,
+        body: &serde_json::Value,
+        output_pointer: &str,
+        timeout: Duration,
+    ) -> Result<AgentResult> {
+        let (result, _) = self
+            .run_with_body(path, body, output_pointer, timeout)
+            .await?;
+        Ok(result)
     }
 
-    let mut results = Vec::new();
-    while let Some(res) = set.join_next().await {
-        results.push(res??);
+    // `run_raw` followed by metrics and every middleware's `after`; also
+    // returns the raw response body, when one was decoded
+    async fn run_with_body(
+        &self,
+        path: &str,
+        body: &serde_json::Value,
+        output_pointer: &str,
+        timeout: Duration,
+    ) -> Result<(AgentResult, Option<serde_json::Value>)> {
+        let outcome = self.run_raw(path, body, output_pointer, timeout).await;
+        #[cfg(feature = "metrics")]
+        metrics::record(
+            &self.config.model,
+            outcome.as_ref().map(|(result, _)| result),
+        );
+        let (result, raw) = outcome?;
+        Ok((self.after(self.cleaned(result)).await, raw))
     }
 
-    Ok(results)
-}
+    // Applied outside `run_raw` so the response cache keeps what the model
+    // sent, whatever the next agent's cleanup settings
+    fn cleaned(&self, result: AgentResult) -> AgentResult {
+        AgentResult {
+            output: result.output.map(|output| self.clean_output(&output)),
+            ..result
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    fn clean_output(&self, mut output: &str) -> String {
+        if self.config.strip_markdown_fences {
+            output = strip_markdown_fences(output);
+        }
+        if self.config.trim_output {
+            output = output.trim();
+        }
+        output.to_string()
+    }
 
-    #[test]
-    fn test_agent_config() {
-        let config = AgentConfig {
-            name: "test".to_string(),
-            model: "qwen2.5-coder:14b".to_string(),
-            ollama_url: "http://localhost:11434".to_string(),
-            temperature: 0.7,
-            timeout_seconds: 60,
+    async fn after(&self, mut result: AgentResult) -> AgentResult {
+        for middleware in &self.middleware {
+            middleware.after(&mut result).await;
+        }
+        result
+    }
+
+    // Shared non-streaming request path: `output_pointer` is the JSON pointer
+    // of the generated text in the response body. Timeouts are reported as a
+    // `TimedOut` result and non-2xx responses as a `Failed` result carrying
+    // the response body, rather than as errors.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "agent.execute",
+            skip_all,
+            fields(
+                agent.name = %self.config.name,
+                model = %self.config.model,
+                path,
+                duration_ms = tracing::field::Empty,
+            )
+        )
+    )]
+    async fn run_raw(
+        &self,
+        path: &str,
+        body: &serde_json::Value,
+        output_pointer: &str,
+        timeout: Duration,
+    ) -> Result<(AgentResult, Option<serde_json::Value>)> {
+        let start = std::time::Instant::now();
+
+        // Middleware may rewrite the request, so the key would not describe
+        // what is sent
+        #[cfg(feature = "cache")]
+        let cache_key = (self.config.cache_capacity > 0 && self.middleware.is_empty())
+            .then(|| cache::cache_key(&self.config, path, body, output_pointer));
+        #[cfg(feature = "cache")]
+        if let Some(key) = cache_key {
+            let ttl = self.config.cache_ttl_seconds.map(Duration::from_secs);
+            if let Some(cached) = cache::shared(self.config.cache_capacity).get(key, ttl) {
+                trace_event!(debug, "response cache hit");
+                let result = AgentResult {
+                    run_id: uuid::Uuid::new_v4(),
+                    agent_id: self.config.name.clone(),
+                    metadata: self.config.metadata.clone(),
+                    ..cached
+                };
+                return Ok((result, None));
+            }
+        }
+
+        let breaker = self.circuit_breaker();
+        if let Some(breaker) = &breaker {
+            if !breaker.allow() {
+                trace_event!(warn, url = %self.config.ollama_url, "circuit open");
+                return Ok((
+                    self.unfinished(AgentStatus::Failed, "circuit open", 0),
+                    None,
+                ));
+            }
+        }
+
+        let mut outcome = self.exchange(path, body, timeout).await;
+        if self.config.auto_pull && matches!(&outcome, Ok(Err(response)) if response.status == 404)
+        {
+            trace_event!(warn, model = %self.config.model, "model missing, pulling");
+            if let Err(e) = self.pull_model(|_| {}).await {
+                let error = format!("auto-pull of '{}' failed: {e}", self.config.model);
+                let duration = start.elapsed().as_millis();
+                return Ok((self.unfinished(AgentStatus::Failed, error, duration), None));
+            }
+            // A fresh budget: the pull itself can take far longer than
+            // `timeout`
+            outcome = self.exchange(path, body, timeout).await;
+        }
+        let mut used_fallback = false;
+        if let (Ok(Err(response)), Some(fallback)) = (&outcome, &self.config.fallback_model) {
+            if model_unavailable(response) && *fallback != self.config.model {
+                trace_event!(warn, status = response.status, fallback = %fallback, "falling back");
+                let mut body = body.clone();
+                body["model"] = serde_json::json!(fallback);
+                outcome = self.exchange(path, &body, timeout).await;
+                used_fallback = true;
+            }
+        }
+
+        let duration = start.elapsed().as_millis();
+        telemetry::record_duration(duration);
+
+        if let Some(breaker) = &breaker {
+            match &outcome {
+                Ok(Err(response)) if response.is_server_error() => breaker.record_failure(),
+                Err(e) if retry::is_retryable_error(e) => breaker.record_failure(),
+                _ => breaker.record_success(),
+            }
+        }
+
+        let result = match outcome {
+            Ok(Err(response)) => {
+                let raw = response.json().ok();
+                let detail = raw
+                    .as_ref()
+                    .and_then(error_message)
+                    .unwrap_or_else(|| response.text());
+                let http_error = format!("HTTP {}: {detail}", response.status);
+                trace_event!(warn, error = %http_error, "agent request rejected");
+                Ok((
+                    self.unfinished(AgentStatus::Failed, http_error, duration),
+                    raw,
+                ))
+            },
+            Ok(Ok(result)) if error_message(&result).is_some() => {
+                let error = error_message(&result).unwrap_or_default();
+                trace_event!(warn, error = %error, "agent request reported an error");
+                Ok((
+                    self.unfinished(AgentStatus::Failed, error, duration),
+                    Some(result),
+                ))
+            },
+            Ok(Ok(result)) => {
+                let output = result
+                    .pointer(output_pointer)
+                    .and_then(|v| v.as_str())
+                    .map(String::from);
+                let error = match &output {
+                    None => Some(format!("response has no text at '{output_pointer}'")),
+                    // Validated as the caller will see it
+                    Some(output) => self.check_output(Some(&self.clean_output(output))),
+                };
+
+                let parsed = AgentResult {
+                    agent_id: self.config.name.clone(),
+                    run_id: uuid::Uuid::new_v4(),
+                    status: if error.is_some() {
+                        AgentStatus::Failed
+                    } else {
+                        AgentStatus::Completed
+                    },
+                    output,
+                    error,
+                    duration_ms: duration,
+                    prompt_tokens: result
+                        .get("prompt_eval_count")
+                        .or_else(|| result.pointer("/usage/prompt_tokens"))
+                        .and_then(|v| v.as_u64()),
+                    completion_tokens: result
+                        .get("eval_count")
+                        .or_else(|| result.pointer("/usage/completion_tokens"))
+                        .and_then(|v| v.as_u64()),
+                    eval_duration_ms: result
+                        .get("eval_duration")
+                        .and_then(|v| v.as_u64())
+                        .map(|ns| ns / 1_000_000),
+                    used_fallback: false,
+                    metadata: self.config.metadata.clone(),
+                    first_token_ms: None,
+                    task_index: None,
+                    schema_version: AgentResult::SCHEMA_VERSION,
+                };
+                Ok((parsed, Some(result)))
+            },
+            Err(e) if is_timeout(&e) => {
+                trace_event!(warn, error = %e, "agent request timed out");
+                Ok((self.unfinished(AgentStatus::TimedOut, e, duration), None))
+            },
+            Err(e @ AgentError::ResponseTooLarge(_)) => {
+                trace_event!(warn, error = %e, "agent response too large");
+                Ok((self.unfinished(AgentStatus::Failed, e, duration), None))
+            },
+            Err(e @ AgentError::Deserialize(_)) => {
+                trace_event!(warn, error = %e, "agent response is not JSON");
+                Ok((self.unfinished(AgentStatus::Failed, e, duration), None))
+            },
+            Err(e) => {
+                trace_event!(error, error = %e, "agent request failed");
+                Err(e)
+            },
         };
+        let result = result.map(|(result, raw)| {
+            let result = AgentResult {
+                used_fallback,
+                ..result
+            };
+            (result, raw)
+        });
 
-        assert_eq!(config.name, "test");
-        assert_eq!(config.temperature, 0.7);
+        #[cfg(feature = "cache")]
+        if let (Some(key), Ok((result, _))) = (cache_key, &result) {
+            if result.status == AgentStatus::Completed {
+                cache::shared(self.config.cache_capacity).insert(key, result.clone());
+            }
+        }
+
+        result
+    }
+
+    // One bounded request: the parsed body for a 2xx response, the response
+    // itself otherwise. The client-side timeout only bounds a single attempt;
+    // this bounds the exchange as a whole, retries included.
+    async fn exchange(
+        &self,
+        path: &str,
+        body: &serde_json::Value,
+        timeout: Duration,
+    ) -> Result<std::result::Result<serde_json::Value, LlmResponse>> {
+        let exchange = async {
+            let response = self.send_with_retry(path, body, timeout).await?;
+            if !response.is_success() {
+                return Ok(Err(response));
+            }
+
+            Ok(Ok(response.json().map_err(|e| not_json(&response, e))?))
+        };
+        tokio::time::timeout(timeout, exchange)
+            .await
+            .unwrap_or(Err(AgentError::Timeout))
+    }
+
+    // Post-response validation of the generated text; `Some` carries the
+    // reason the result should be marked `Failed`.
+    fn check_output(&self, output: Option<&str>) -> Option<String> {
+        if !self.config.validate_json {
+            return None;
+        }
+        match serde_json::from_str::<serde_json::Value>(output.unwrap_or_default()) {
+            Ok(_) => None,
+            Err(e) => Some(format!("output is not valid JSON: {e}")),
+        }
+    }
+
+    // Execute, folding any error into a `Failed` result. Batch runners use
+    // this so every input yields exactly one result.
+    pub(crate) async fn execute_settled(&self, task: &str) -> AgentResult {
+        let start = std::time::Instant::now();
+        let outcome = self.execute(task).await;
+        self.settle(outcome, start)
+    }
+
+    pub(crate) fn settle(
+        &self,
+        outcome: Result<AgentResult>,
+        start: std::time::Instant,
+    ) -> AgentResult {
+        outcome.unwrap_or_else(|e| {
+            self.unfinished(AgentStatus::Failed, e, start.elapsed().as_millis())
+        })
+    }
+
+    // Result for an execution that produced no output
+    fn unfinished(
+        &self,
+        status: AgentStatus,
+        error: impl std::fmt::Display,
+        duration_ms: u128,
+    ) -> AgentResult {
+        AgentResult {
+            agent_id: self.config.name.clone(),
+            run_id: uuid::Uuid::new_v4(),
+            status,
+            output: None,
+            error: Some(error.to_string()),
+            duration_ms,
+            prompt_tokens: None,
+            completion_tokens: None,
+            eval_duration_ms: None,
+            used_fallback: false,
+            metadata: self.config.metadata.clone(),
+            first_token_ms: None,
+            task_index: None,
+            schema_version: AgentResult::SCHEMA_VERSION,
+        }
+    }
+
+    /// Runs [`RustAgent::execute`] until it finishes or `cancel` fires. On
+    /// cancellation the in-flight request is dropped and a `Cancelled` result
+    /// is returned.
+    pub async fn execute_with_cancel(
+        &self,
+        task: &str,
+        cancel: &CancellationToken,
+    ) -> Result<AgentResult> {
+        let start = std::time::Instant::now();
+
+        tokio::select! {
+            result = self.execute(task) => result,
+            _ = cancel.cancelled() => Ok(self.unfinished(
+                AgentStatus::Cancelled,
+                "execution cancelled",
+                start.elapsed().as_millis(),
+            )),
+        }
+    }
+
+    /// Like [`RustAgent::execute`], but yields `response` chunks as Ollama
+    /// produces them. The stream closes cleanly on the `done: true` line; HTTP
+    /// and decoding failures mid-stream are delivered as `Err` items.
+    pub async fn execute_stream(&self, task: &str) -> Result<impl Stream<Item = Result<String>>> {
+        Ok(streaming::response_chunks(self.open_stream(task).await?))
+    }
+
+    // The undecoded body of a streaming generate request. Unlike the chunk
+    // stream it doesn't borrow the agent, so it can outlive the call.
+    async fn open_stream(&self, task: &str) -> Result<ByteStream> {
+        let request = self
+            .request(
+                "/api/generate",
+                &self.generate_body(task, true),
+                self.timeout(),
+            )
+            .await;
+        self.with_retry(
+            || self.backend.generate_stream(request.clone()),
+            |outcome| matches!(outcome, Err(e) if self.config.retry_policy.should_retry(e)),
+        )
+        .await
+    }
+
+    /// Like [`RustAgent::execute_stream`], but also yields a
+    /// [`crate::StreamProgress`] with the live token rate roughly every
+    /// `interval`, right after the chunk that crossed it.
+    pub async fn execute_stream_with_progress(
+        &self,
+        task: &str,
+        interval: Duration,
+    ) -> Result<impl Stream<Item = Result<StreamEvent>>> {
+        let chunks = self.execute_stream(task).await?;
+        Ok(streaming::with_progress(chunks, interval))
+    }
+
+    /// Runs [`RustAgent::execute_stream`] to the end and gathers the chunks
+    /// into one result, recording `first_token_ms` as well as the total
+    /// duration. A failure mid-stream yields a `Failed` result keeping the
+    /// output received so far. Token counts are not reported.
+    pub async fn execute_streamed(&self, task: &str) -> Result<AgentResult> {
+        self.execute_streamed_until(task, |_| false).await
+    }
+
+    /// Like [`RustAgent::execute_streamed`], but checks `stop_when` against
+    /// the output accumulated so far after every chunk. Once it returns
+    /// `true` the request is abandoned, which stops generation on the server,
+    /// and the output up to and including that chunk comes back `Completed`.
+    /// Saves the tokens a model would spend rambling past e.g. a closing code
+    /// fence.
+    pub async fn execute_streamed_until(
+        &self,
+        task: &str,
+        stop_when: impl Fn(&str) -> bool,
+    ) -> Result<AgentResult> {
+        let start = std::time::Instant::now();
+        let mut output = String::new();
+        let mut first_token_ms = None;
+
+        let collect = async {
+            let stream = self.execute_stream(task).await?;
+            futures::pin_mut!(stream);
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                if first_token_ms.is_none() && !chunk.is_empty() {
+                    first_token_ms = Some(start.elapsed().as_millis() as u64);
+                }
+                output.push_str(&chunk);
+                // Dropping the stream closes the connection
+                if stop_when(&output) {
+                    break;
+                }
+            }
+            Ok(())
+        };
+        let outcome = tokio::time::timeout(self.timeout(), collect)
+            .await
+            .unwrap_or(Err(AgentError::Timeout));
+
+        let duration = start.elapsed().as_millis();
+        let (status, error) = match outcome {
+            Ok(()) => (AgentStatus::Completed, None),
+            Err(AgentError::Timeout) => (AgentStatus::TimedOut, Some(AgentError::Timeout)),
+            Err(
+                e @ (AgentError::HttpStatus(_)
+                | AgentError::Backend(_)
+                | AgentError::ResponseTooLarge(_)),
+            ) => (AgentStatus::Failed, Some(e)),
+            // A transport error after some output still produced a result
+            Err(e) if first_token_ms.is_some() => (AgentStatus::Failed, Some(e)),
+            Err(e) => {
+                #[cfg(feature = "metrics")]
+                metrics::record(&self.config.model, Err(&e));
+                return Err(e);
+            },
+        };
+
+        let result = AgentResult {
+            output: (!output.is_empty() || status == AgentStatus::Completed)
+                .then(|| self.clean_output(&output)),
+            error: error.map(|e| e.to_string()),
+            first_token_ms,
+            ..self.unfinished(status, "", duration)
+        };
+        #[cfg(feature = "metrics")]
+        metrics::record(&self.config.model, Ok(&result));
+        Ok(self.after(result).await)
+    }
+
+    /// Checks that the backend is reachable and can serve the configured
+    /// model by sending the smallest possible request, which also loads the
+    /// model so the first real request doesn't pay for a cold start.
+    /// Returns `false` if the server rejects it (e.g. the model isn't
+    /// pulled); transport failures are errors.
+    pub async fn health_check(&self) -> Result<bool> {
+        let (path, body) = match self.config.api_flavor {
+            // An empty prompt makes Ollama load the model without generating
+            ApiFlavor::OllamaNative => {
+                let mut body = serde_json::json!({
+                    "model": self.config.model,
+                    "prompt": "",
+                    "stream": false,
+                });
+                if let Some(keep_alive) = self.keep_alive() {
+                    body["keep_alive"] = keep_alive;
+                }
+                ("/api/generate", body)
+            },
+            ApiFlavor::OpenAiCompatible => (
+                OPENAI_CHAT_PATH,
+                serde_json::json!({
+                    "model": self.config.model,
+                    "messages": [ChatMessage::new("user", "ping")],
+                    "max_tokens": 1,
+                }),
+            ),
+        };
+
+        let response = self
+            .backend
+            .generate(self.request(path, &body, self.timeout()).await)
+            .await?;
+        trace_event!(debug, status = response.status, "health check");
+        Ok(response.is_success())
+    }
+
+    /// Downloads the configured model with Ollama's `/api/pull`, calling
+    /// `on_progress` with each status line, and returns once Ollama reports
+    /// `success`. Pulls can take minutes, so this is bounded by an hour
+    /// rather than `timeout_seconds`.
+    pub async fn pull_model(&self, mut on_progress: impl FnMut(&PullProgress)) -> Result<()> {
+        let body = serde_json::json!({ "model": self.config.model, "stream": true });
+        let request = self.request("/api/pull", &body, PULL_TIMEOUT).await;
+        let mut stream = self.backend.generate_stream(request).await?;
+
+        let mut succeeded = false;
+        let mut handle_line = |line: &[u8]| -> Result<()> {
+            if let Some(progress) = models::parse_pull_line(line)? {
+                succeeded |= progress.status == "success";
+                on_progress(&progress);
+            }
+            Ok(())
+        };
+        let mut buffer = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            buffer.extend_from_slice(&chunk?);
+            while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=pos).collect();
+                handle_line(&line)?;
+            }
+        }
+        handle_line(&buffer)?;
+
+        if !succeeded {
+            return Err(AgentError::Backend(format!(
+                "pull of '{}' ended without success",
+                self.config.model
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the embedding vector for `text` from `/api/embeddings`. Fails
+    /// if the configured model does not produce embeddings.
+    pub async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let response = self
+            .send_with_retry(
+                "/api/embeddings",
+                &serde_json::json!({
+                    "model": self.config.model,
+                    "prompt": text,
+                }),
+                self.timeout(),
+            )
+            .await?;
+
+        let body = response.json();
+        if let Some(error) = body
+            .as_ref()
+            .ok()
+            .and_then(|body| body.get("error"))
+            .and_then(|v| v.as_str())
+        {
+            return Err(AgentError::Backend(format!(
+                "embedding request for model '{}' failed: {error}",
+                self.config.model
+            )));
+        }
+        if !response.is_success() {
+            return Err(AgentError::HttpStatus(response.status));
+        }
+        let body = body?;
+
+        let embedding: Vec<f32> = body
+            .get("embedding")
+            .map(|v| serde_json::from_value(v.clone()))
+            .transpose()?
+            .unwrap_or_default();
+        if embedding.is_empty() {
+            return Err(AgentError::Backend(format!(
+                "model '{}' returned no embedding; it may not support embeddings",
+                self.config.model
+            )));
+        }
+
+        Ok(embedding)
+    }
+
+    fn generate_body(&self, task: &str, stream: bool) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "model": self.config.model,
+            "prompt": task,
+            "options": self.request_options(),
+            "stream": stream,
+        });
+        if let Some(system) = &self.config.system {
+            body["system"] = serde_json::json!(system);
+        }
+        if self.config.options.raw {
+            body["raw"] = serde_json::json!(true);
+        }
+        self.apply_common_fields(&mut body);
+        body
+    }
+
+    // The configured system prompt, if any, is sent as a leading system
+    // message ahead of the caller's history.
+    fn chat_body(&self, messages: &[ChatMessage], stream: bool) -> serde_json::Value {
+        let system = self
+            .config
+            .system
+            .as_ref()
+            .map(|system| ChatMessage::new("system", system.as_str()));
+        let messages: Vec<&ChatMessage> = system.iter().chain(messages).collect();
+
+        let mut body = serde_json::json!({
+            "model": self.config.model,
+            "messages": messages,
+            "options": self.request_options(),
+            "stream": stream,
+        });
+        self.apply_common_fields(&mut body);
+        body
+    }
+
+    // `/v1/chat/completions` body. Only the sampling options with an OpenAI
+    // equivalent are sent; `top_k`, `repeat_penalty`, `num_ctx`, and
+    // `keep_alive` have none.
+    fn openai_body(&self, messages: &[ChatMessage]) -> serde_json::Value {
+        let system = self
+            .config
+            .system
+            .as_ref()
+            .map(|system| ChatMessage::new("system", system.as_str()));
+        let messages: Vec<&ChatMessage> = system.iter().chain(messages).collect();
+        let options = &self.config.options;
+
+        let mut body = serde_json::json!({
+            "model": self.config.model,
+            "messages": messages,
+            "temperature": self.config.temperature,
+            "stream": false,
+        });
+        if let Some(top_p) = options.top_p {
+            body["top_p"] = serde_json::json!(top_p);
+        }
+        if let Some(num_predict) = options.num_predict.filter(|n| *n > 0) {
+            body["max_tokens"] = serde_json::json!(num_predict);
+        }
+        if let Some(seed) = options.seed {
+            body["seed"] = serde_json::json!(seed);
+        }
+        if !options.stop.is_empty() {
+            body["stop"] = serde_json::json!(options.stop);
+        }
+        match &self.config.format {
+            OutputFormat::Text => {},
+            OutputFormat::Json => {
+                body["response_format"] = serde_json::json!({ "type": "json_object" })
+            },
+            OutputFormat::JsonSchema(schema) => {
+                body["response_format"] = serde_json::json!({
+                    "type": "json_schema",
+                    "json_schema": { "name": "output", "schema": schema },
+                })
+            },
+        }
+        body
+    }
+
+    // `template` with the placeholders of every string filled in. Strings with
+    // a placeholder `validate` would reject are sent unchanged.
+    fn fill_request_template(&self, template: &serde_json::Value, task: &str) -> serde_json::Value {
+        use serde_json::Value;
+
+        let vars = HashMap::from([
+            ("prompt".to_string(), task.to_string()),
+            ("model".to_string(), self.config.model.clone()),
+            (
+                "temperature".to_string(),
+                self.config.temperature.to_string(),
+            ),
+        ]);
+        match template {
+            Value::String(text) if text == "{temperature}" => {
+                serde_json::json!(self.config.temperature)
+            },
+            Value::String(text) => Value::String(
+                PromptTemplate::new(text.as_str())
+                    .render(&vars)
+                    .unwrap_or_else(|_| text.clone()),
+            ),
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| self.fill_request_template(item, task))
+                    .collect(),
+            ),
+            Value::Object(fields) => Value::Object(
+                fields
+                    .iter()
+                    .map(|(key, value)| (key.clone(), self.fill_request_template(value, task)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    // Fields shared by the generate and chat request bodies. Ollama takes
+    // `keep_alive` as either a duration string or a number of seconds, where
+    // a negative number means "forever", so numeric values are sent as
+    // numbers.
+    fn apply_common_fields(&self, body: &mut serde_json::Value) {
+        match &self.config.format {
+            OutputFormat::Text => {},
+            OutputFormat::Json => body["format"] = serde_json::json!("json"),
+            OutputFormat::JsonSchema(schema) => body["format"] = schema.clone(),
+        }
+        if let Some(keep_alive) = self.keep_alive() {
+            body["keep_alive"] = keep_alive;
+        }
+    }
+
+    fn keep_alive(&self) -> Option<serde_json::Value> {
+        self.config
+            .keep_alive
+            .as_ref()
+            .map(|keep_alive| match keep_alive.trim().parse::<i64>() {
+                Ok(seconds) => serde_json::json!(seconds),
+                Err(_) => serde_json::json!(keep_alive),
+            })
+    }
+
+    // The `options` object for a request: temperature plus whichever
+    // `GenerationOptions` fields are set. `raw` is a top-level request field,
+    // so it is taken back out.
+    fn request_options(&self) -> serde_json::Value {
+        let mut options =
+            serde_json::to_value(&self.config.options).unwrap_or_else(|_| serde_json::json!({}));
+        if let Some(options) = options.as_object_mut() {
+            options.remove("raw");
+        }
+        options["temperature"] = serde_json::json!(self.config.temperature);
+        options
+    }
+
+    // The breaker shared by every agent targeting this `ollama_url`, if
+    // enabled
+    fn circuit_breaker(&self) -> Option<Arc<circuit::CircuitBreaker>> {
+        (self.config.failure_threshold > 0).then(|| {
+            circuit::breaker_for(
+                &self.config.ollama_url,
+                self.config.failure_threshold,
+                Duration::from_millis(self.config.cooldown_ms),
+            )
+        })
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.config.timeout_seconds)
+    }
+
+    // The request for `body`, after every middleware's `before`
+    async fn request(&self, path: &str, body: &serde_json::Value, timeout: Duration) -> LlmRequest {
+        let mut request = self.raw_request(path, body, timeout);
+        for middleware in &self.middleware {
+            middleware.before(&mut request).await;
+        }
+        request
+    }
+
+    fn raw_request(&self, path: &str, body: &serde_json::Value, timeout: Duration) -> LlmRequest {
+        let mut headers: Vec<(String, String)> = self
+            .config
+            .headers
+            .iter()
+            .filter(|(name, _)| {
+                self.config.bearer_token.is_none() || !name.eq_ignore_ascii_case("authorization")
+            })
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        if let Some(token) = &self.config.bearer_token {
+            headers.push(("Authorization".to_string(), format!("Bearer {token}")));
+        }
+
+        LlmRequest {
+            base_url: self.config.ollama_url.clone(),
+            path: path.to_string(),
+            body: body.clone(),
+            timeout,
+            headers,
+            max_response_bytes: self.config.max_response_bytes,
+        }
+    }
+
+    // POSTs `body` to `path`, retrying transport failures and 5xx responses
+    // with exponential backoff. Any other response is returned as-is for the
+    // caller to interpret.
+    async fn send_with_retry(
+        &self,
+        path: &str,
+        body: &serde_json::Value,
+        timeout: Duration,
+    ) -> Result<LlmResponse> {
+        let request = self.request(path, body, timeout).await;
+
+        self.with_retry(
+            || self.backend.generate(request.clone()),
+            |outcome| match outcome {
+                Ok(response) => {
+                    trace_event!(debug, status = response.status, "response received");
+                    !response.is_success()
+                        && self
+                            .config
+                            .retry_policy
+                            .should_retry(&AgentError::HttpStatus(response.status))
+                },
+                Err(e) => self.config.retry_policy.should_retry(e),
+            },
+        )
+        .await
+    }
+
+    // Calls `send` until it produces an outcome `retryable` rejects or
+    // `max_retries` is exhausted, sleeping with exponential backoff between
+    // attempts.
+    async fn with_retry<T, F, Fut>(
+        &self,
+        mut send: F,
+        retryable: impl Fn(&Result<T>) -> bool,
+    ) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        let limiter = self
+            .config
+            .requests_per_second
+            // `validate` rejects other rates; treat them as unlimited here
+            .filter(|rate| rate_limit::is_valid_rate(*rate))
+            .map(|rate| rate_limit::limiter_for(&self.config.ollama_url, rate));
+
+        loop {
+            if let Some(limiter) = &limiter {
+                limiter.acquire().await;
+            }
+            trace_event!(debug, url = %self.config.ollama_url, attempt, "sending request");
+            let outcome = send().await;
+
+            if !retryable(&outcome)
+                || attempt >= self.config.max_retries
+                || !self.retry_budget.as_ref().is_none_or(|b| b.try_spend())
+            {
+                return outcome;
+            }
+
+            let mut delay = retry::backoff_delay(self.config.retry_backoff_ms, attempt);
+            if self.config.retry_jitter {
+                delay = retry::jittered(delay, &mut rand::rng());
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+// A 2xx body that failed to parse, e.g. a proxy's HTML error page, quoted
+// with its content type so the cause is recognisable
+fn not_json(response: &LlmResponse, err: AgentError) -> AgentError {
+    let text = response.text();
+    let text = text.trim();
+    let mut snippet: String = text.chars().take(BODY_SNIPPET_CHARS).collect();
+    if snippet.len() < text.len() {
+        snippet.push('…');
+    }
+    let detail = match err {
+        AgentError::Deserialize(detail) => detail,
+        other => other.to_string(),
+    };
+    AgentError::Deserialize(format!(
+        "{detail} (content-type: {}): {snippet:?}",
+        response.content_type.as_deref().unwrap_or("none")
+    ))
+}
+
+// The client `RustAgent::new` gives an agent: the connection-level settings
+// of `config` applied to reqwest's defaults
+pub(crate) fn build_client(config: &AgentConfig) -> Result<reqwest::Client> {
+    let user_agent = config.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT);
+    let mut builder = reqwest::Client::builder().user_agent(user_agent);
+    if let Some(seconds) = config.connect_timeout_seconds {
+        builder = builder.connect_timeout(Duration::from_secs(seconds));
+    }
+    if let Some(seconds) = config.read_timeout_seconds {
+        builder = builder.read_timeout(Duration::from_secs(seconds));
+    }
+    if let Some(seconds) = config.pool_idle_timeout_seconds {
+        builder = builder.pool_idle_timeout(Duration::from_secs(seconds));
+    }
+    if let Some(max_idle) = config.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max_idle);
+    }
+    if let Some(proxy_url) = &config.proxy_url {
+        let proxy = reqwest::Proxy::all(proxy_url)
+            .map_err(|e| config.invalid(format!("invalid proxy_url '{proxy_url}': {e}")))?;
+        let no_proxy = config
+            .no_proxy
+            .as_deref()
+            .and_then(reqwest::NoProxy::from_string);
+        builder = builder.proxy(proxy.no_proxy(no_proxy));
+    }
+    if let Some(pem) = &config.tls_root_cert_pem {
+        let certs = reqwest::Certificate::from_pem_bundle(pem.as_bytes())
+            .map_err(|e| config.invalid(format!("invalid tls_root_cert_pem: {e}")))?;
+        if certs.is_empty() {
+            return Err(config.invalid("tls_root_cert_pem contains no certificates"));
+        }
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    if config.tls_accept_invalid_certs {
+        builder = builder.tls_danger_accept_invalid_certs(true);
+    }
+
+    builder
+        .build()
+        .map_err(|e| AgentError::Config(format!("failed to build HTTP client: {e}")))
+}
+
+// Calls `visit` with every string value nested in `value`
+fn template_strings(value: &serde_json::Value, visit: &mut impl FnMut(&str)) {
+    match value {
+        serde_json::Value::String(text) => visit(text),
+        serde_json::Value::Array(items) => {
+            items.iter().for_each(|item| template_strings(item, visit))
+        },
+        serde_json::Value::Object(fields) => fields
+            .values()
+            .for_each(|field| template_strings(field, visit)),
+        _ => {},
+    }
+}
+
+// Converts a `response_path` such as `choices[0].text` to the JSON pointer
+// `/choices/0/text`; the error describes what is malformed
+fn response_pointer(path: &str) -> std::result::Result<String, String> {
+    let malformed = || format!("malformed response_path '{path}'");
+    let mut pointer = String::new();
+    for segment in path.split('.') {
+        let (key, mut indices) = segment.split_at(segment.find('[').unwrap_or(segment.len()));
+        if key.is_empty() && indices.is_empty() {
+            return Err(malformed());
+        }
+        if key.contains(']') {
+            return Err(malformed());
+        }
+        if !key.is_empty() {
+            pointer.push('/');
+            pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+        }
+        while !indices.is_empty() {
+            let close = indices.find(']').ok_or_else(malformed)?;
+            let index = &indices[1..close];
+            if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(malformed());
+            }
+            pointer.push('/');
+            pointer.push_str(index);
+            indices = &indices[close + 1..];
+            if !indices.is_empty() && !indices.starts_with('[') {
+                return Err(malformed());
+            }
+        }
+    }
+    Ok(pointer)
+}
+
+// The contents of the only fenced block in `output`, without the opening
+// line's info string (e.g. `rust`). Output with no fences, an unclosed fence,
+// or several blocks is returned unchanged, since the prose between blocks
+// may matter.
+fn strip_markdown_fences(output: &str) -> &str {
+    let mut fences = output
+        .match_indices("```")
+        .map(|(pos, _)| pos)
+        // Only fences that open a line count
+        .filter(|&pos| {
+            output[..pos]
+                .rsplit('\n')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .is_empty()
+        });
+    let (Some(open), Some(close), None) = (fences.next(), fences.next(), fences.next()) else {
+        return output;
+    };
+    match output[open..close].split_once('\n') {
+        Some((_, body)) => body.strip_suffix('\n').unwrap_or(body),
+        None => output,
+    }
+}
+
+// FNV-1a: unlike `DefaultHasher`, stable across processes and Rust versions
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash: u64, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+// `text` in pieces of at most `max_chars` characters
+fn split_chars(text: &str, max_chars: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let end = rest
+            .char_indices()
+            .nth(max_chars)
+            .map_or(rest.len(), |(i, _)| i);
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
+}
+
+// The `error` a server put in its reply body: a string for Ollama, an object
+// with a `message` for OpenAI-compatible servers
+fn error_message(body: &serde_json::Value) -> Option<String> {
+    let error = body.get("error")?;
+    error
+        .as_str()
+        .or_else(|| error.get("message").and_then(|v| v.as_str()))
+        .map(String::from)
+}
+
+// Ollama answers 404 for a model that isn't pulled, and an error mentioning
+// the model or memory when it can't be loaded (e.g. OOM)
+fn model_unavailable(response: &LlmResponse) -> bool {
+    if response.status == 404 {
+        return true;
+    }
+    let text = response.text().to_lowercase();
+    response.status >= 400 && (text.contains("model") || text.contains("memory"))
+}
+
+fn is_timeout(err: &AgentError) -> bool {
+    matches!(err, AgentError::Timeout)
+}
+
+/// Polls [`RustAgent::health_check`] for `model` at `ollama_url` every
+/// `poll_interval` until the server answers and serves the model, for
+/// starting a batch only once a freshly started server is up. Unreachable
+/// servers and missing models are both polled again; once `timeout` has
+/// passed this fails with [`AgentError::Timeout`].
+pub async fn wait_until_ready(
+    ollama_url: &str,
+    model: &str,
+    timeout: Duration,
+    poll_interval: Duration,
+) -> Result<()> {
+    if poll_interval.is_zero() {
+        return Err(AgentError::Config(
+            "poll_interval must be greater than zero".to_string(),
+        ));
+    }
+    let agent = RustAgent::new(AgentConfig {
+        name: "readiness".to_string(),
+        model: model.to_string(),
+        ollama_url: ollama_url.to_string(),
+        ..AgentConfig::default()
+    })?;
+
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        // A check that loads the model may take a while; it is cut off at
+        // the deadline rather than at the agent's own timeout
+        match tokio::time::timeout_at(deadline, agent.health_check()).await {
+            Ok(Ok(true)) => return Ok(()),
+            Ok(_) => {},
+            Err(_) => return Err(AgentError::Timeout),
+        }
+        if tokio::time::Instant::now() + poll_interval >= deadline {
+            return Err(AgentError::Timeout);
+        }
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+// Stand-in result for a spawned agent task that panicked or was aborted. The
+// agent went down with its task, so its name is unknown.
+pub(crate) fn join_failure(err: tokio::task::JoinError) -> AgentResult {
+    AgentResult {
+        agent_id: String::new(),
+        run_id: uuid::Uuid::new_v4(),
+        status: AgentStatus::Failed,
+        output: None,
+        error: Some(format!("agent task failed: {err}")),
+        duration_ms: 0,
+        prompt_tokens: None,
+        completion_tokens: None,
+        eval_duration_ms: None,
+        used_fallback: false,
+        metadata: HashMap::new(),
+        first_token_ms: None,
+        task_index: None,
+        schema_version: AgentResult::SCHEMA_VERSION,
+    }
+}
+
+// The `execute_parallel*` family pairs agents with tasks by position;
+// mismatched inputs would otherwise silently drop the extras.
+pub(crate) fn check_lengths(agents: usize, tasks: usize) -> Result<()> {
+    if agents != tasks {
+        return Err(AgentError::Config(format!(
+            "got {agents} agents and {tasks} tasks; lengths must match"
+        )));
+    }
+    Ok(())
+}
+
+// Drains `set` in completion order, one result per spawned task
+pub(crate) async fn collect_completed(mut set: JoinSet<AgentResult>) -> Vec<AgentResult> {
+    let mut results = Vec::with_capacity(set.len());
+    while let Some(res) = set.join_next().await {
+        results.push(res.unwrap_or_else(join_failure));
+    }
+    results
+}
+
+// Parallel processing for multiple agents
+//
+// Every agent is spawned at once; use `execute_parallel_limited` to bound how
+// many requests hit the backend simultaneously. A failing or panicking agent
+// yields a `Failed` result in place of its output, so the returned vector
+// always has one entry per input. `agents` and `tasks` must have the same
+// length; every variant below fails with `AgentError::Config` otherwise.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "agent.parallel", skip_all, fields(agents = agents.len()))
+)]
+pub async fn execute_parallel(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+) -> Result<Vec<AgentResult>> {
+    check_lengths(agents.len(), tasks.len())?;
+    let mut set = JoinSet::new();
+
+    for (index, (agent, task)) in agents.into_iter().zip(tasks).enumerate() {
+        set.spawn(telemetry::in_current_span(async move {
+            agent.execute_settled(&task).await.for_task(index)
+        }));
+    }
+
+    Ok(collect_completed(set).await)
+}
+
+/// The streaming counterpart of [`execute_parallel`]: every agent streams
+/// its task concurrently and the chunks are merged as they arrive, each
+/// tagged with the agent's id. A failed request shows up as a single `Err`
+/// for that agent while the others carry on. Nothing is sent until the
+/// stream is first polled.
+pub fn execute_parallel_stream(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+) -> Result<impl Stream<Item = (String, Result<String>)>> {
+    check_lengths(agents.len(), tasks.len())?;
+    let streams = agents.into_iter().zip(tasks).map(|(agent, task)| {
+        let id = agent.config.name.clone();
+        futures::stream::once(async move {
+            match agent.open_stream(&task).await {
+                Ok(body) => streaming::response_chunks(body).boxed(),
+                Err(e) => futures::stream::iter([Err(e)]).boxed(),
+            }
+        })
+        .flatten()
+        .map(move |chunk| (id.clone(), chunk))
+        .boxed()
+    });
+
+    Ok(futures::stream::select_all(streams))
+}
+
+/// Same as [`execute_parallel`], but the whole batch may retry at most
+/// `retry_budget` times in total, on top of each agent's own `max_retries`
+/// and `retry_policy`. Once the budget is spent, further failures are returned
+/// without retrying, which caps retry amplification against a recovering
+/// backend.
+pub async fn execute_parallel_with_retry_budget(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+    retry_budget: usize,
+) -> Result<Vec<AgentResult>> {
+    execute_parallel(share_retry_budget(agents, retry_budget), tasks).await
+}
+
+// Gives every agent the same budget of `retries`
+pub(crate) fn share_retry_budget(agents: Vec<RustAgent>, retries: usize) -> Vec<RustAgent> {
+    let budget = Arc::new(retry::RetryBudget::new(retries));
+    agents
+        .into_iter()
+        .map(|mut agent| {
+            agent.retry_budget = Some(budget.clone());
+            agent
+        })
+        .collect()
+}
+
+/// Same as [`execute_parallel`], but every agent observes `cancel`. Agents
+/// still running when it fires come back with status `Cancelled`.
+pub async fn execute_parallel_with_cancel(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+    cancel: CancellationToken,
+) -> Result<Vec<AgentResult>> {
+    execute_parallel_with_progress(agents, tasks, cancel, |_| {}).await
+}
+
+/// Same as [`execute_parallel_with_cancel`], but calls `on_result` with each
+/// result as soon as its agent finishes, in completion order. The callback
+/// runs on the collecting task, one call at a time, so it should be quick.
+pub async fn execute_parallel_with_progress(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+    cancel: CancellationToken,
+    mut on_result: impl FnMut(&AgentResult),
+) -> Result<Vec<AgentResult>> {
+    check_lengths(agents.len(), tasks.len())?;
+    let mut set = JoinSet::new();
+
+    for (index, (agent, task)) in agents.into_iter().zip(tasks).enumerate() {
+        let cancel = cancel.clone();
+        set.spawn(telemetry::in_current_span(async move {
+            let start = std::time::Instant::now();
+            let outcome = agent.execute_with_cancel(&task, &cancel).await;
+            agent.settle(outcome, start).for_task(index)
+        }));
+    }
+
+    let mut results = Vec::with_capacity(set.len());
+    while let Some(res) = set.join_next().await {
+        let result = res.unwrap_or_else(join_failure);
+        on_result(&result);
+        results.push(result);
+    }
+    Ok(results)
+}
+
+/// Same as [`execute_parallel`], but each task is bounded by its own entry in
+/// `timeouts` instead of the agent's `timeout_seconds`. `agents`, `tasks`,
+/// and `timeouts` must all have the same length.
+pub async fn execute_parallel_with_timeouts(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+    timeouts: Vec<Duration>,
+) -> Result<Vec<AgentResult>> {
+    if agents.len() != tasks.len() || tasks.len() != timeouts.len() {
+        return Err(AgentError::Config(format!(
+            "got {} agents, {} tasks, and {} timeouts; lengths must match",
+            agents.len(),
+            tasks.len(),
+            timeouts.len()
+        )));
+    }
+
+    let mut set = JoinSet::new();
+
+    for (index, ((agent, task), timeout)) in agents.into_iter().zip(tasks).zip(timeouts).enumerate()
+    {
+        set.spawn(telemetry::in_current_span(async move {
+            let start = std::time::Instant::now();
+            let outcome = agent.execute_with_timeout(&task, timeout).await;
+            agent.settle(outcome, start).for_task(index)
+        }));
+    }
+
+    Ok(collect_completed(set).await)
+}
+
+/// Same as [`execute_parallel`], but the whole batch is bounded by
+/// `deadline`: whatever finished by then is returned as-is and every agent
+/// still running comes back `TimedOut`. Unlike per-request timeouts, this caps
+/// total wall-clock time however far along each agent is.
+pub async fn execute_parallel_deadline(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+    deadline: Duration,
+) -> Result<Vec<AgentResult>> {
+    check_lengths(agents.len(), tasks.len())?;
+    let start = tokio::time::Instant::now();
+    let deadline = start + deadline;
+    let mut set = JoinSet::new();
+
+    for (index, (agent, task)) in agents.into_iter().zip(tasks).enumerate() {
+        set.spawn(telemetry::in_current_span(async move {
+            let result = match tokio::time::timeout_at(deadline, agent.execute_settled(&task)).await
+            {
+                Ok(result) => result,
+                Err(_) => agent.unfinished(
+                    AgentStatus::TimedOut,
+                    "batch deadline exceeded",
+                    start.elapsed().as_millis(),
+                ),
+            };
+            result.for_task(index)
+        }));
+    }
+
+    Ok(collect_completed(set).await)
+}
+
+/// Same as [`execute_parallel`], but at most `max_concurrent` agents run at
+/// any one time, started in input order, and the batch shuts down
+/// gracefully: once `shutdown` fires no further agents are started, and
+/// those already running get up to `drain_deadline` to finish before being
+/// cancelled. Every input still yields a result; agents that never started
+/// or were cut off come back as `Cancelled`. With `max_concurrent` of
+/// `usize::MAX` every agent starts at once, so only a `shutdown` that fired
+/// before the call keeps any from starting.
+pub async fn execute_parallel_graceful(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+    max_concurrent: usize,
+    drain_deadline: Duration,
+    shutdown: CancellationToken,
+) -> Result<Vec<AgentResult>> {
+    check_lengths(agents.len(), tasks.len())?;
+    if max_concurrent == 0 {
+        return Err(AgentError::Config(
+            "max_concurrent must be at least 1".to_string(),
+        ));
+    }
+    let drain = CancellationToken::new();
+    // Started first, so the deadline runs even while admission is blocked
+    let watcher = tokio::spawn({
+        let drain = drain.clone();
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown.cancelled().await;
+            tokio::time::sleep(drain_deadline).await;
+            drain.cancel();
+        }
+    });
+
+    let semaphore = Arc::new(Semaphore::new(max_concurrent.min(Semaphore::MAX_PERMITS)));
+    let mut set = JoinSet::new();
+    let mut not_started = Vec::new();
+
+    for (index, (agent, task)) in agents.into_iter().zip(tasks).enumerate() {
+        // Checked before every start, including while waiting for a permit
+        let permit = tokio::select! {
+            biased;
+            _ = shutdown.cancelled() => None,
+            permit = Arc::clone(&semaphore).acquire_owned() => permit.ok(),
+        };
+        let Some(permit) = permit else {
+            not_started.push(
+                agent
+                    .unfinished(AgentStatus::Cancelled, "shutdown before start", 0)
+                    .for_task(index),
+            );
+            continue;
+        };
+        let drain = drain.clone();
+        set.spawn(telemetry::in_current_span(async move {
+            let _permit = permit;
+            let start = std::time::Instant::now();
+            let outcome = agent.execute_with_cancel(&task, &drain).await;
+            agent.settle(outcome, start).for_task(index)
+        }));
+    }
+
+    let mut results = collect_completed(set).await;
+    watcher.abort();
+    results.extend(not_started);
+    Ok(results)
+}
+
+/// Same as [`execute_parallel`], but builds every agent from `configs` around
+/// the one shared `client`, so agents pointing at the same host reuse
+/// keep-alive connections instead of each opening their own pool.
+pub async fn execute_parallel_shared(
+    configs: Vec<AgentConfig>,
+    tasks: Vec<String>,
+    client: reqwest::Client,
+) -> Result<Vec<AgentResult>> {
+    let agents = configs
+        .into_iter()
+        .map(|config| RustAgent::with_client(config, client.clone()))
+        .collect();
+
+    execute_parallel(agents, tasks).await
+}
+
+/// Same as [`execute_parallel`], but results are returned in the order of
+/// the input `agents`/`tasks` rather than in completion order.
+pub async fn execute_parallel_ordered(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+) -> Result<Vec<AgentResult>> {
+    check_lengths(agents.len(), tasks.len())?;
+    let mut set = JoinSet::new();
+    let mut indices = HashMap::new();
+
+    for (index, (agent, task)) in agents.into_iter().zip(tasks).enumerate() {
+        let handle = set.spawn(telemetry::in_current_span(async move {
+            agent.execute_settled(&task).await
+        }));
+        indices.insert(handle.id(), index);
+    }
+
+    let mut slots: Vec<Option<AgentResult>> = (0..indices.len()).map(|_| None).collect();
+    while let Some(res) = set.join_next_with_id().await {
+        let (id, result) = match res {
+            Ok((id, result)) => (id, result),
+            Err(e) => (e.id(), join_failure(e)),
+        };
+        let index = indices[&id];
+        slots[index] = Some(result.for_task(index));
+    }
+
+    Ok(slots.into_iter().flatten().collect())
+}
+
+/// Same as [`execute_parallel`], but stops at the first agent that does not
+/// complete: every other agent is aborted and that agent's error is returned.
+/// A `TimedOut` result becomes [`AgentError::Timeout`] and any other
+/// unsuccessful result an [`AgentError::Backend`] naming the agent. When
+/// everything completes, the results come back in completion order.
+pub async fn execute_parallel_fail_fast(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+) -> Result<Vec<AgentResult>> {
+    check_lengths(agents.len(), tasks.len())?;
+    let mut set = JoinSet::new();
+
+    for (index, (agent, task)) in agents.into_iter().zip(tasks).enumerate() {
+        set.spawn(telemetry::in_current_span(async move {
+            agent
+                .execute(&task)
+                .await
+                .map(|result| result.for_task(index))
+        }));
+    }
+
+    let mut results = Vec::with_capacity(set.len());
+    while let Some(res) = set.join_next().await {
+        let failure = match res {
+            Ok(Ok(result)) => match result.status {
+                AgentStatus::Completed => {
+                    results.push(result);
+                    continue;
+                },
+                AgentStatus::TimedOut => AgentError::Timeout,
+                status => AgentError::Backend(format!(
+                    "agent '{}' {status}: {}",
+                    result.agent_id,
+                    result.error.unwrap_or_default()
+                )),
+            },
+            Ok(Err(e)) => e,
+            Err(e) => AgentError::Backend(format!("agent task failed: {e}")),
+        };
+        set.abort_all();
+        return Err(failure);
+    }
+    Ok(results)
+}
+
+/// Same as [`execute_parallel_ordered`], but identical requests are sent only
+/// once and the result is copied to every position that asked for it.
+/// Requests count as identical when their [`AgentConfig::fingerprint`] for
+/// the task matches and so does every setting that shapes the result:
+/// `response_path`, `strip_markdown_fences`, `trim_output`, `validate_json`,
+/// prompt chunking, `max_response_bytes`, `fallback_model`, and `auto_pull`.
+/// Agents with [`Middleware`] installed are never merged, since it may
+/// rewrite their requests. Copies carry their own agent's `agent_id` and
+/// `metadata` and a fresh `run_id`.
+pub async fn execute_parallel_deduplicated(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+) -> Result<Vec<AgentResult>> {
+    check_lengths(agents.len(), tasks.len())?;
+    let mut unique: HashMap<u64, usize> = HashMap::new();
+    let mut unique_agents = Vec::new();
+    let mut unique_tasks = Vec::new();
+    // For each input: its unique request and, for duplicates, the agent_id
+    // and metadata to report
+    let mut positions = Vec::new();
+
+    for (agent, task) in agents.into_iter().zip(tasks) {
+        let key = agent
+            .middleware
+            .is_empty()
+            .then(|| agent.config.result_fingerprint(&task));
+        match key.and_then(|key| unique.get(&key)) {
+            Some(&index) => positions.push((
+                index,
+                Some((agent.config.name.clone(), agent.config.metadata.clone())),
+            )),
+            None => {
+                if let Some(key) = key {
+                    unique.insert(key, unique_agents.len());
+                }
+                positions.push((unique_agents.len(), None));
+                unique_agents.push(agent);
+                unique_tasks.push(task);
+            },
+        }
+    }
+
+    let results = execute_parallel_ordered(unique_agents, unique_tasks).await?;
+    Ok(positions
+        .into_iter()
+        .enumerate()
+        .map(|(position, (index, duplicate_of))| {
+            match duplicate_of {
+                None => results[index].clone(),
+                Some((agent_id, metadata)) => AgentResult {
+                    agent_id,
+                    metadata,
+                    run_id: uuid::Uuid::new_v4(),
+                    ..results[index].clone()
+                },
+            }
+            .for_task(position)
+        })
+        .collect())
+}
+
+/// Same as [`execute_parallel`], but at most `max_concurrent` agents run at
+/// any one time; the rest wait for a permit and are started in
+/// [`AgentConfig::priority`] order, ties keeping their input order.
+/// `execute_parallel` is equivalent to this with
+/// `max_concurrent >= agents.len()`.
+pub async fn execute_parallel_limited(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+    max_concurrent: usize,
+) -> Result<Vec<AgentResult>> {
+    check_lengths(agents.len(), tasks.len())?;
+    if max_concurrent == 0 {
+        return Err(AgentError::Config(
+            "max_concurrent must be at least 1".to_string(),
+        ));
+    }
+
+    let mut queue: Vec<_> = agents.into_iter().zip(tasks).enumerate().collect();
+    queue.sort_by_key(|(_, (agent, _))| agent.config.priority);
+
+    // Permits are taken here, in queue order, rather than inside each task so
+    // the spawn order decides who runs next
+    let semaphore = Arc::new(Semaphore::new(max_concurrent));
+    let mut set = JoinSet::new();
+
+    for (index, (agent, task)) in queue {
+        // The semaphore is never closed, so acquiring cannot fail
+        let permit = Arc::clone(&semaphore).acquire_owned().await;
+        set.spawn(telemetry::in_current_span(async move {
+            let _permit = permit;
+            agent.execute_settled(&task).await.for_task(index)
+        }));
+    }
+
+    Ok(collect_completed(set).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockBackend, MockReply};
+
+    fn mock_agent(backend: MockBackend) -> (RustAgent, Arc<MockBackend>) {
+        let backend = Arc::new(backend);
+        let config = AgentConfig {
+            name: "test".to_string(),
+            model: "qwen2.5-coder:14b".to_string(),
+            ..Default::default()
+        };
+        (RustAgent::with_backend(config, backend.clone()), backend)
+    }
+
+    #[tokio::test]
+    async fn test_fail_fast_aborts_the_rest() {
+        let (slow, slow_backend) =
+            mock_agent(MockBackend::with_response("ok").with_delay(Duration::from_secs(5)));
+        let (failing, failing_backend) = mock_agent(MockBackend::with_response("ok"));
+        failing_backend.push_json(500, serde_json::json!({"error": "boom"}));
+
+        let start = std::time::Instant::now();
+        let err = execute_parallel_fail_fast(
+            vec![slow, failing],
+            vec!["slow".to_string(), "fails".to_string()],
+        )
+        .await
+        .unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(err.to_string().contains("boom"), "{err}");
+        assert_eq!(slow_backend.requests().len(), 1);
+
+        let (agent, _backend) = mock_agent(MockBackend::with_response("ok"));
+        let results =
+            execute_parallel_fail_fast(vec![agent.clone(), agent], vec!["a".into(), "b".into()])
+                .await
+                .unwrap();
+        assert_eq!(results.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_deduplicated_sends_identical_requests_once() {
+        let (agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        let tasks = ["same", "other", "same"].map(String::from).to_vec();
+
+        let results = execute_parallel_deduplicated(vec![agent; 3], tasks)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(backend.requests().len(), 2);
+        assert_eq!(results[2].output, results[0].output);
+        assert_ne!(results[2].run_id, results[0].run_id);
+
+        let (agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        let mut other_tenant = agent.clone();
+        other_tenant.config.bearer_token = Some("other".to_string());
+        let mut other_path = agent.clone();
+        other_path.config.response_path = Some("message.content".to_string());
+        let results = execute_parallel_deduplicated(
+            vec![agent, other_tenant, other_path],
+            vec!["same".to_string(); 3],
+        )
+        .await
+        .unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(backend.requests().len(), 3, "nothing is merged");
+        let indices: Vec<_> = results.iter().map(|r| r.task_index).collect();
+        assert_eq!(indices, [Some(0), Some(1), Some(2)]);
+
+        let (agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        let guarded = agent.clone().with_middleware(Guardrail);
+        execute_parallel_deduplicated(vec![agent, guarded], vec!["same".to_string(); 2])
+            .await
+            .unwrap();
+        let mut prompts: Vec<_> = backend
+            .requests()
+            .iter()
+            .map(|r| r.body["prompt"].as_str().unwrap().to_string())
+            .collect();
+        prompts.sort();
+        assert_eq!(prompts, ["Be safe. same", "same"]);
+    }
+
+    #[tokio::test]
+    async fn test_limited_starts_higher_priority_first() {
+        let backend =
+            Arc::new(MockBackend::with_response("ok").with_delay(Duration::from_millis(5)));
+        let agents = [
+            Priority::Low,
+            Priority::Normal,
+            Priority::High,
+            Priority::Normal,
+        ]
+        .into_iter()
+        .map(|priority| {
+            let config = AgentConfig {
+                priority,
+                ..Default::default()
+            };
+            RustAgent::with_backend(config, backend.clone())
+        })
+        .collect();
+        let tasks = ["low", "normal-1", "high", "normal-2"]
+            .map(String::from)
+            .to_vec();
+
+        let results = execute_parallel_limited(agents, tasks, 1).await.unwrap();
+        assert_eq!(results.len(), 4);
+        // Completion order follows start order with one permit; the index
+        // still points back at the input
+        assert_eq!(results[0].task_index, Some(2));
+
+        let prompts: Vec<_> = backend
+            .requests()
+            .iter()
+            .map(|r| r.body["prompt"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(prompts, ["high", "normal-1", "normal-2", "low"]);
+    }
+
+    #[tokio::test]
+    async fn test_wait_until_ready_times_out_on_an_unreachable_server() {
+        let start = std::time::Instant::now();
+        let err = wait_until_ready(
+            "http://127.0.0.1:9",
+            "qwen2.5-coder:14b",
+            Duration::from_millis(200),
+            Duration::from_millis(50),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err, AgentError::Timeout);
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        let err = wait_until_ready(
+            "http://127.0.0.1:9",
+            "m",
+            Duration::from_secs(1),
+            Duration::ZERO,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, AgentError::Config(_)));
+    }
+
+    #[test]
+    fn test_agent_config() {
+        let config = AgentConfig {
+            name: "test".to_string(),
+            model: "qwen2.5-coder:14b".to_string(),
+            ollama_url: "http://localhost:11434".to_string(),
+            temperature: 0.7,
+            timeout_seconds: 60,
+            ..Default::default()
+        };
+
+        assert_eq!(config.name, "test");
+        assert_eq!(config.temperature, 0.7);
+    }
+
+    #[test]
+    fn test_agent_status_serializes_lowercase() {
+        assert_eq!(
+            serde_json::to_string(&AgentStatus::Completed).unwrap(),
+            "\"completed\""
+        );
+        assert_eq!(
+            serde_json::to_string(&AgentStatus::TimedOut).unwrap(),
+            "\"timed_out\""
+        );
+        assert_eq!(AgentStatus::Cancelled.to_string(), "cancelled");
+    }
+
+    #[test]
+    fn test_agent_config_builder_validation() {
+        let config = AgentConfig::builder()
+            .name("coder")
+            .model("qwen2.5-coder:14b")
+            .temperature(0.2)
+            .build()
+            .unwrap();
+        assert_eq!(config.ollama_url, "http://localhost:11434");
+
+        let err = AgentConfig::builder()
+            .name("coder")
+            .model("qwen2.5-coder:14b")
+            .temperature(50.0)
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("temperature"));
+
+        assert!(AgentConfig::builder().name("coder").build().is_err());
+        assert!(AgentConfig::builder()
+            .name("coder")
+            .model("m")
+            .timeout_seconds(0)
+            .build()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_mock_backend() {
+        let (agent, backend) = mock_agent(MockBackend::with_response("fn main() {}"));
+
+        let result = agent.execute("write main").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Completed);
+        assert_eq!(result.agent_id, "test");
+        assert_eq!(result.output.as_deref(), Some("fn main() {}"));
+
+        let requests = backend.requests();
+        assert_eq!(requests[0].path, "/api/generate");
+        assert_eq!(requests[0].body["prompt"], "write main");
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_execute_blocking() {
+        let (agent, _backend) = mock_agent(MockBackend::with_response("ok"));
+        let result = agent.execute_blocking("a").unwrap();
+        assert_eq!(result.output.as_deref(), Some("ok"));
+
+        let nested = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(async { agent.execute_blocking("a") });
+        assert!(nested.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_headers_and_bearer_token_are_sent() {
+        let (mut agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        agent.config.headers = HashMap::from([
+            ("X-Tenant".to_string(), "acme".to_string()),
+            ("Authorization".to_string(), "Basic stale".to_string()),
+        ]);
+        agent.config.bearer_token = Some("secret".to_string());
+
+        agent.execute("a").await.unwrap();
+
+        let mut headers = backend.requests()[0].headers.clone();
+        headers.sort();
+        assert_eq!(
+            headers,
+            vec![
+                ("Authorization".to_string(), "Bearer secret".to_string()),
+                ("X-Tenant".to_string(), "acme".to_string()),
+            ]
+        );
+
+        let mut config = agent.config.clone();
+        config.headers = HashMap::from([("bad header".to_string(), "x".to_string())]);
+        assert!(config.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fallback_model_on_unavailable_model() {
+        let (mut agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        agent.config.fallback_model = Some("qwen2.5-coder:1.5b".to_string());
+        backend.push_json(404, serde_json::json!({"error": "model not found"}));
+
+        let result = agent.execute("a").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Completed);
+        assert!(result.used_fallback);
+        let requests = backend.requests();
+        assert_eq!(requests[1].body["model"], "qwen2.5-coder:1.5b");
+
+        backend.push(MockReply::Timeout);
+        let result = agent.execute("a").await.unwrap();
+        assert_eq!(result.status, AgentStatus::TimedOut);
+        assert!(!result.used_fallback);
+        assert_eq!(backend.requests().len(), 3);
+
+        // A slow primary still leaves the fallback a full timeout
+        let (mut agent, backend) =
+            mock_agent(MockBackend::with_response("ok").with_delay(Duration::from_millis(600)));
+        agent.config.fallback_model = Some("qwen2.5-coder:1.5b".to_string());
+        agent.config.timeout_seconds = 1;
+        backend.push_json(404, serde_json::json!({"error": "model not found"}));
+        let result = agent.execute("a").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Completed);
+        assert!(result.used_fallback);
+        assert!(result.duration_ms >= 1000);
+    }
+
+    #[tokio::test]
+    async fn test_output_cleanup_keeps_the_raw_body() {
+        let reply = "Sure, here's the code:\n```rust\nfn main() {}\n```\nLet me know!";
+        let (mut agent, _backend) = mock_agent(MockBackend::with_response(reply));
+        agent.config.strip_markdown_fences = true;
+        agent.config.trim_output = true;
+
+        let (result, raw) = agent.execute_raw("a").await.unwrap();
+        assert_eq!(result.output.as_deref(), Some("fn main() {}"));
+        assert_eq!(raw["response"], reply);
+
+        assert_eq!(strip_markdown_fences("  ```\nx\n```  "), "x");
+        let two_blocks = "```\na\n```\nthen\n```\nb\n```";
+        assert_eq!(strip_markdown_fences(two_blocks), two_blocks);
+        assert_eq!(
+            strip_markdown_fences("inline ``` fence"),
+            "inline ``` fence"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_reproducible_forces_seed_and_zero_temperature() {
+        let (agent, backend) = mock_agent(MockBackend::with_response("ok"));
+
+        agent.execute_reproducible("a", 42).await.unwrap();
+        let options = &backend.requests()[0].body["options"];
+        assert_eq!(options["seed"], 42);
+        assert_eq!(options["temperature"], 0.0);
+
+        let err = agent.execute_reproducible("a", u64::MAX).await.unwrap_err();
+        assert!(matches!(err, AgentError::Config(_)));
+    }
+
+    #[tokio::test]
+    async fn test_long_prompts_fail_or_split() {
+        let (mut agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        agent.config.max_prompt_chars = Some(4);
+
+        let result = agent.execute("abcdefghij").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Failed);
+        assert!(result.error.unwrap().contains("prompt too long"));
+        assert!(backend.requests().is_empty());
+
+        agent.config.chunk_strategy = ChunkStrategy::Split;
+        let result = agent.execute("abcdéfghij").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Completed);
+        assert_eq!(result.output.as_deref(), Some("ok\nok\nok"));
+        let prompts: Vec<_> = backend
+            .requests()
+            .iter()
+            .map(|r| r.body["prompt"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(prompts, ["abcd", "éfgh", "ij"]);
+    }
+
+    #[test]
+    fn test_cooldown_is_bounded() {
+        let config = AgentConfig {
+            name: "test".to_string(),
+            model: "qwen2.5-coder:14b".to_string(),
+            failure_threshold: 1,
+            cooldown_ms: u64::MAX,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("cooldown_ms"), "{err}");
+
+        let config = AgentConfig {
+            name: "test".to_string(),
+            model: "qwen2.5-coder:14b".to_string(),
+            cooldown_ms: 24 * 60 * 60 * 1000,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_invalid_proxy_url_is_a_config_error() {
+        let config = AgentConfig {
+            name: "test".to_string(),
+            model: "qwen2.5-coder:14b".to_string(),
+            proxy_url: Some("not a url".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(config.validate(), Err(AgentError::Config(_))));
+        assert!(matches!(RustAgent::new(config), Err(AgentError::Config(_))));
+
+        let config = AgentConfig {
+            proxy_url: Some("http://proxy.corp:3128".to_string()),
+            no_proxy: Some("localhost,10.0.0.0/8".to_string()),
+            ..Default::default()
+        };
+        assert!(RustAgent::new(config).is_ok());
+    }
+
+    #[test]
+    fn test_user_agent_must_be_a_valid_header_value() {
+        let builder = || {
+            AgentConfig::builder()
+                .name("test")
+                .model("qwen2.5-coder:14b")
+        };
+        assert!(builder().user_agent("ops-batch/1.0").build().is_ok());
+        assert!(matches!(
+            builder().user_agent("bad\nagent").build(),
+            Err(AgentError::Config(_))
+        ));
+        assert!(DEFAULT_USER_AGENT.starts_with("self-hosted-ai-agents/"));
+    }
+
+    #[test]
+    fn test_pool_settings_build_a_shared_client() {
+        let config = AgentConfig {
+            name: "test".to_string(),
+            model: "qwen2.5-coder:14b".to_string(),
+            pool_idle_timeout_seconds: Some(0),
+            ..Default::default()
+        };
+        assert!(matches!(config.validate(), Err(AgentError::Config(_))));
+
+        let config = AgentConfig {
+            name: "test".to_string(),
+            model: "qwen2.5-coder:14b".to_string(),
+            pool_idle_timeout_seconds: Some(30),
+            pool_max_idle_per_host: Some(4),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        assert!(crate::AgentPool::from_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_root_cert_pem_without_certificates_is_a_config_error() {
+        let config = AgentConfig {
+            tls_root_cert_pem: Some("not a certificate".to_string()),
+            ..Default::default()
+        };
+        let err = RustAgent::new(config).err().unwrap();
+        assert!(err.to_string().contains("tls_root_cert_pem"), "{err}");
+    }
+
+    #[test]
+    fn test_response_pointer() {
+        assert_eq!(response_pointer("response").unwrap(), "/response");
+        assert_eq!(
+            response_pointer("choices[0].text").unwrap(),
+            "/choices/0/text"
+        );
+        assert_eq!(response_pointer("data[1][2]").unwrap(), "/data/1/2");
+        for bad in ["", "a..b", "a[", "a[x]", "a[0]b", "a]"] {
+            assert!(response_pointer(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_response_path_selects_field_or_fails() {
+        let (mut agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        agent.config.response_path = Some("choices[0].text".to_string());
+
+        backend.push_json(
+            200,
+            serde_json::json!({"choices": [{"text": "from gateway"}]}),
+        );
+        let result = agent.execute("a").await.unwrap();
+        assert_eq!(result.output.as_deref(), Some("from gateway"));
+
+        let result = agent.execute("a").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Failed);
+        assert!(result.error.unwrap().contains("/choices/0/text"));
+    }
+
+    #[tokio::test]
+    async fn test_metadata_is_copied_to_results_but_not_sent() {
+        let (mut agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        agent.config.metadata = HashMap::from([("user".to_string(), "u-42".to_string())]);
+
+        let result = agent.execute("a").await.unwrap();
+        assert_eq!(result.metadata["user"], "u-42");
+        assert!(!backend.requests()[0].body.to_string().contains("u-42"));
+
+        backend.push(MockReply::Timeout);
+        let result = agent.execute("a").await.unwrap();
+        assert_eq!(result.metadata["user"], "u-42");
+    }
+
+    #[tokio::test]
+    async fn test_execute_streamed_records_first_token_latency() {
+        let (agent, _backend) =
+            mock_agent(MockBackend::with_response("hello").with_delay(Duration::from_millis(20)));
+
+        let result = agent.execute_streamed("a").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Completed);
+        assert_eq!(result.output.as_deref(), Some("hello"));
+        let first_token_ms = result.first_token_ms.unwrap();
+        assert!(first_token_ms >= 20);
+        assert!(first_token_ms as u128 <= result.duration_ms);
+    }
+
+    #[tokio::test]
+    async fn test_execute_streamed_until_stops_at_the_predicate() {
+        let (agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        let lines: Vec<String> = ["Here:\n```", "\nfn main() {}\n", "```", " More words."]
+            .iter()
+            .map(|chunk| serde_json::json!({ "response": chunk, "done": false }).to_string())
+            .chain([serde_json::json!({ "response": "", "done": true }).to_string()])
+            .collect();
+        backend.push(MockReply::Response(LlmResponse {
+            status: 200,
+            content_type: Some("application/x-ndjson".to_string()),
+            body: lines.join("\n").into_bytes(),
+        }));
+
+        let result = agent
+            .execute_streamed_until("a", |output| output.matches("```").count() == 2)
+            .await
+            .unwrap();
+        assert_eq!(result.status, AgentStatus::Completed);
+        assert_eq!(
+            result.output.as_deref(),
+            Some("Here:\n```\nfn main() {}\n```")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_error_field_in_body_fails_the_result() {
+        let (agent, backend) = mock_agent(MockBackend::with_response("ok"));
+
+        backend.push_json(404, serde_json::json!({"error": "model 'foo' not found"}));
+        let result = agent.execute("a").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Failed);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("HTTP 404: model 'foo' not found")
+        );
+
+        backend.push_json(200, serde_json::json!({"error": "model 'foo' not found"}));
+        let result = agent.execute("a").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Failed);
+        assert_eq!(result.error.as_deref(), Some("model 'foo' not found"));
+        assert_eq!(result.output, None);
+    }
+
+    #[tokio::test]
+    async fn test_graceful_drain_cancels_after_deadline() {
+        let (agent, backend) =
+            mock_agent(MockBackend::with_response("ok").with_delay(Duration::from_secs(5)));
+        let shutdown = CancellationToken::new();
+        let trigger = shutdown.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            trigger.cancel();
+        });
+
+        let results = execute_parallel_graceful(
+            vec![agent.clone(), agent],
+            vec!["a".to_string(), "b".to_string()],
+            usize::MAX,
+            Duration::from_millis(10),
+            shutdown,
+        )
+        .await
+        .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.status == AgentStatus::Cancelled));
+        assert_eq!(backend.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_graceful_starts_nothing_after_shutdown() {
+        let (agent, backend) =
+            mock_agent(MockBackend::with_response("ok").with_delay(Duration::from_millis(200)));
+        let shutdown = CancellationToken::new();
+        let trigger = shutdown.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            trigger.cancel();
+        });
+
+        let mut results = execute_parallel_graceful(
+            vec![agent; 4],
+            ["a", "b", "c", "d"].map(String::from).to_vec(),
+            1,
+            Duration::from_secs(5),
+            shutdown,
+        )
+        .await
+        .unwrap();
+        results.sort_by_key(|r| r.task_index);
+        let statuses: Vec<_> = results.iter().map(|r| r.status).collect();
+        // "b" was running when shutdown fired and drained; "c" and "d" never
+        // started
+        assert_eq!(
+            statuses,
+            [
+                AgentStatus::Completed,
+                AgentStatus::Completed,
+                AgentStatus::Cancelled,
+                AgentStatus::Cancelled,
+            ]
+        );
+        assert_eq!(results[3].error.as_deref(), Some("shutdown before start"));
+        assert_eq!(backend.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_images_encodes_and_rejects() {
+        let (agent, backend) = mock_agent(MockBackend::with_response("a cat"));
+
+        let result = agent
+            .execute_with_images("describe", vec![b"png".to_vec()])
+            .await
+            .unwrap();
+        assert_eq!(result.output.as_deref(), Some("a cat"));
+        assert_eq!(backend.requests()[0].body["images"][0], "cG5n");
+
+        assert!(agent.execute_with_images("describe", vec![]).await.is_err());
+        backend.push_json(
+            500,
+            serde_json::json!({"error": "this model is missing data required for image input"}),
+        );
+        assert!(matches!(
+            agent
+                .execute_with_images("describe", vec![b"png".to_vec()])
+                .await,
+            Err(AgentError::Backend(_))
+        ));
+
+        // Other failures that merely mention images stay ordinary results
+        backend.push_json(400, serde_json::json!({"error": "image: unknown format"}));
+        let result = agent
+            .execute_with_images("describe", vec![b"png".to_vec()])
+            .await
+            .unwrap();
+        assert_eq!(result.status, AgentStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_openai_compatible_flavor() {
+        let (mut agent, backend) = mock_agent(MockBackend::with_response("unused"));
+        agent.config.api_flavor = ApiFlavor::OpenAiCompatible;
+        agent.config.system = Some("be brief".to_string());
+        backend.push_json(
+            200,
+            serde_json::json!({
+                "choices": [{ "message": { "role": "assistant", "content": "hi" } }],
+                "usage": { "prompt_tokens": 5, "completion_tokens": 1 },
+            }),
+        );
+
+        let result = agent.execute("hello").await.unwrap();
+        assert_eq!(result.output.as_deref(), Some("hi"));
+        assert_eq!(result.completion_tokens, Some(1));
+
+        let request = &backend.requests()[0];
+        assert_eq!(request.path, "/v1/chat/completions");
+        assert_eq!(request.body["messages"][0]["role"], "system");
+        assert_eq!(request.body["messages"][1]["content"], "hello");
+    }
+
+    #[tokio::test]
+    async fn test_health_check() {
+        let (agent, backend) = mock_agent(MockBackend::with_response(""));
+        assert!(agent.health_check().await.unwrap());
+        assert_eq!(backend.requests()[0].body["prompt"], "");
+
+        backend.push_json(404, serde_json::json!({"error": "model not found"}));
+        assert!(!agent.health_check().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_parallel_deadline_keeps_finished_results() {
+        let (fast, _fast_backend) = mock_agent(MockBackend::with_response("done"));
+        let (slow, _slow_backend) =
+            mock_agent(MockBackend::with_response("late").with_delay(Duration::from_secs(5)));
+
+        let started = std::time::Instant::now();
+        let mut results = execute_parallel_deadline(
+            vec![fast, slow],
+            vec!["a".to_string(), "b".to_string()],
+            Duration::from_millis(50),
+        )
+        .await
+        .unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        results.sort_by_key(|r| r.status.as_str());
+        assert_eq!(results[0].output.as_deref(), Some("done"));
+        assert_eq!(results[1].status, AgentStatus::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn test_auto_pull_pulls_missing_model_then_retries() {
+        let (mut agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        agent.config.auto_pull = true;
+        backend.push_json(
+            404,
+            serde_json::json!({"error": "model 'llama3' not found"}),
+        );
+        backend.push(MockReply::Response(LlmResponse {
+            status: 200,
+            content_type: Some("application/x-ndjson".to_string()),
+            body: b"{\"status\":\"pulling manifest\"}\n{\"status\":\"success\"}\n".to_vec(),
+        }));
+
+        let result = agent.execute("hi").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Completed);
+        let paths: Vec<String> = backend.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(paths, ["/api/generate", "/api/pull", "/api/generate"]);
+
+        // A pull that outlasts the timeout still leaves the retry its own
+        let (mut agent, backend) =
+            mock_agent(MockBackend::with_response("ok").with_delay(Duration::from_millis(400)));
+        agent.config.auto_pull = true;
+        agent.config.timeout_seconds = 1;
+        backend.push_json(404, serde_json::json!({"error": "model not found"}));
+        backend.push(MockReply::Response(LlmResponse {
+            status: 200,
+            content_type: Some("application/x-ndjson".to_string()),
+            body: b"{\"status\":\"success\"}\n".to_vec(),
+        }));
+        let result = agent.execute("hi").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Completed);
+        assert!(result.duration_ms >= 1000);
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_non_request_fields() {
+        let config = AgentConfig::default();
+        let renamed = AgentConfig {
+            name: "other".to_string(),
+            max_retries: 3,
+            metadata: HashMap::from([("user".to_string(), "42".to_string())]),
+            ..config.clone()
+        };
+        assert_eq!(config.fingerprint("hi"), renamed.fingerprint("hi"));
+        assert_ne!(config.fingerprint("hi"), config.fingerprint("bye"));
+
+        let hotter = AgentConfig {
+            temperature: 1.5,
+            ..config.clone()
+        };
+        assert_ne!(config.fingerprint("hi"), hotter.fingerprint("hi"));
+
+        let tenant = |token: &str| AgentConfig {
+            bearer_token: Some(token.to_string()),
+            ..config.clone()
+        };
+        assert_ne!(tenant("a").fingerprint("hi"), tenant("b").fingerprint("hi"));
+        assert_ne!(config.fingerprint("hi"), tenant("a").fingerprint("hi"));
+        let with_header = AgentConfig {
+            headers: HashMap::from([("X-Tenant".to_string(), "acme".to_string())]),
+            ..config.clone()
+        };
+        assert_ne!(config.fingerprint("hi"), with_header.fingerprint("hi"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_raw_returns_the_untouched_body() {
+        let (agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        backend.push_json(
+            200,
+            serde_json::json!({"response": "hi", "context": [1, 2, 3], "total_duration": 42}),
+        );
+
+        let (result, raw) = agent.execute_raw("hello").await.unwrap();
+        assert_eq!(result.output.as_deref(), Some("hi"));
+        assert_eq!(raw["context"], serde_json::json!([1, 2, 3]));
+        assert_eq!(raw["total_duration"], 42);
+    }
+
+    #[tokio::test]
+    async fn test_parallel_rejects_mismatched_lengths() {
+        let (agent, _backend) = mock_agent(MockBackend::with_response("ok"));
+
+        let mismatched =
+            execute_parallel(vec![agent.clone(), agent.clone()], vec!["a".to_string()]).await;
+        assert!(matches!(mismatched, Err(AgentError::Config(_))));
+
+        let results = execute_parallel(vec![agent.clone(), agent], vec!["a".into(), "b".into()])
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_parallel_with_timeouts_checks_lengths() {
+        let (agent, _backend) =
+            mock_agent(MockBackend::with_response("ok").with_delay(Duration::from_millis(50)));
+
+        let mismatched =
+            execute_parallel_with_timeouts(vec![agent.clone()], vec!["a".to_string()], vec![])
+                .await;
+        assert!(matches!(mismatched, Err(AgentError::Config(_))));
+
+        let mut results = execute_parallel_with_timeouts(
+            vec![agent.clone(), agent],
+            vec!["short".to_string(), "long".to_string()],
+            vec![Duration::from_millis(5), Duration::from_secs(5)],
+        )
+        .await
+        .unwrap();
+        results.sort_by_key(|r| r.status.as_str());
+        assert_eq!(results[0].status, AgentStatus::Completed);
+        assert_eq!(results[1].status, AgentStatus::TimedOut);
+    }
+
+    #[test]
+    fn test_build_request_shape() {
+        let config = AgentConfig {
+            name: "test".to_string(),
+            model: "qwen2.5-coder:14b".to_string(),
+            temperature: 0.5,
+            system: Some("be brief".to_string()),
+            options: GenerationOptions {
+                num_predict: Some(64),
+                num_ctx: Some(8192),
+                stop: vec!["```".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let agent = RustAgent::with_backend(config, Arc::new(MockBackend::with_response("")));
+
+        assert_eq!(
+            agent.build_request("hi"),
+            serde_json::json!({
+                "model": "qwen2.5-coder:14b",
+                "prompt": "hi",
+                "system": "be brief",
+                "stream": false,
+                "options": {
+                    "temperature": 0.5,
+                    "num_predict": 64,
+                    "num_ctx": 8192,
+                    "stop": ["```"],
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_request_template_fills_placeholders_and_is_validated() {
+        let (mut agent, _) = mock_agent(MockBackend::with_response(""));
+        agent.config.temperature = 0.5;
+        agent.config.request_template = Some(serde_json::json!({
+            "engine": "{model}",
+            "input": { "text": "Q: {prompt}", "sampling": { "temp": "{temperature}" } },
+            "tags": ["batch", "{{literal}}"],
+        }));
+        assert!(agent.config.validate().is_ok());
+        assert_eq!(
+            agent.build_request("hi"),
+            serde_json::json!({
+                "engine": "qwen2.5-coder:14b",
+                "input": { "text": "Q: hi", "sampling": { "temp": 0.5 } },
+                "tags": ["batch", "{literal}"],
+            })
+        );
+
+        for template in [
+            serde_json::json!({ "model": "{model}" }),
+            serde_json::json!({ "prompt": "{prompt}", "user": "{user}" }),
+        ] {
+            agent.config.request_template = Some(template);
+            assert!(matches!(
+                agent.config.validate(),
+                Err(AgentError::Config(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_raw_mode_is_sent_beside_options() {
+        let (mut agent, _) = mock_agent(MockBackend::with_response(""));
+        agent.config.options.raw = true;
+
+        let body = agent.build_request("<|im_start|>user\nhi<|im_end|>");
+        assert_eq!(body["raw"], true);
+        assert!(body["options"].get("raw").is_none());
+    }
+
+    #[cfg(feature = "cache")]
+    #[tokio::test]
+    async fn test_completed_results_are_cached() {
+        let (mut agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        agent.config.cache_capacity = 8;
+        agent.config.options.seed = Some(7);
+
+        backend.push_json(500, serde_json::json!({"error": "boom"}));
+        let first = agent.execute("cache me").await.unwrap();
+        assert_eq!(first.status, AgentStatus::Failed);
+
+        let second = agent.execute("cache me").await.unwrap();
+        let third = agent.execute("cache me").await.unwrap();
+        assert_eq!(third.output, second.output);
+        assert_ne!(third.run_id, second.run_id);
+        assert_eq!(backend.requests().len(), 2, "failure is not cached");
+
+        agent.config.bearer_token = Some("other tenant".to_string());
+        agent.execute("cache me").await.unwrap();
+        assert_eq!(
+            backend.requests().len(),
+            3,
+            "credentials are part of the key"
+        );
+
+        agent.config.ollama_url = "http://other-host:11434".to_string();
+        agent.execute("cache me").await.unwrap();
+        assert_eq!(backend.requests().len(), 4, "so is the server");
+
+        agent.config.response_path = Some("message.content".to_string());
+        agent.execute("cache me").await.unwrap();
+        assert_eq!(backend.requests().len(), 5, "and where output is read");
+    }
+
+    #[cfg(feature = "cache")]
+    #[tokio::test]
+    async fn test_agents_with_middleware_bypass_the_cache() {
+        let (mut agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        agent.config.cache_capacity = 8;
+        agent.config.options.seed = Some(8);
+        let guarded = agent.clone().with_middleware(Guardrail);
+
+        agent.execute("guard me").await.unwrap();
+        guarded.execute("guard me").await.unwrap();
+        guarded.execute("guard me").await.unwrap();
+        let prompts: Vec<_> = backend
+            .requests()
+            .iter()
+            .map(|r| r.body["prompt"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            prompts,
+            ["guard me", "Be safe. guard me", "Be safe. guard me"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_reports_token_usage() {
+        let (agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        backend.push_json(
+            200,
+            serde_json::json!({
+                "response": "ok",
+                "done": true,
+                "prompt_eval_count": 12,
+                "eval_count": 34,
+                "eval_duration": 56_000_000u64,
+            }),
+        );
+
+        let result = agent.execute("a").await.unwrap();
+        assert_eq!(result.prompt_tokens, Some(12));
+        assert_eq!(result.completion_tokens, Some(34));
+        assert_eq!(result.eval_duration_ms, Some(56));
+
+        let result = agent.execute("a").await.unwrap();
+        assert_eq!(result.prompt_tokens, None);
+    }
+
+    #[tokio::test]
+    async fn test_execute_reports_http_failures_and_timeouts() {
+        let (agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        backend
+            .push_json(404, serde_json::json!({ "error": "model not found" }))
+            .push(MockReply::Timeout);
+
+        let failed = agent.execute("a").await.unwrap();
+        assert_eq!(failed.status, AgentStatus::Failed);
+        assert!(failed.error.unwrap().contains("model not found"));
+
+        let timed_out = agent.execute("b").await.unwrap();
+        assert_eq!(timed_out.status, AgentStatus::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn test_non_json_reply_fails_with_a_body_snippet() {
+        let (agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        let page = format!("<html><body>{}</body></html>", "Bad Gateway ".repeat(40));
+        backend.push(MockReply::Response(LlmResponse {
+            status: 200,
+            content_type: Some("text/html".to_string()),
+            body: page.into_bytes(),
+        }));
+
+        let result = agent.execute("a").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Failed);
+        let error = result.error.unwrap();
+        assert!(error.contains("content-type: text/html"), "{error}");
+        assert!(error.contains("<html><body>Bad Gateway"), "{error}");
+        assert!(error.len() < 400, "snippet is truncated: {error}");
+    }
+
+    #[tokio::test]
+    async fn test_execute_retries_transport_errors() {
+        let (mut agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        agent.config.max_retries = 2;
+        agent.config.retry_backoff_ms = 1;
+        backend.push(MockReply::ConnectionError("refused".to_string()));
+
+        let result = agent.execute("a").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Completed);
+        assert_eq!(backend.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_parallel_stream_tags_chunks_by_agent() {
+        let (agent, _) = mock_agent(MockBackend::with_response("ok"));
+        let mut other = agent.clone();
+        other.config.name = "other".to_string();
+        let (failing, _) = mock_agent(MockBackend::with_reply(MockReply::ConnectionError(
+            "refused".to_string(),
+        )));
+
+        let stream = execute_parallel_stream(
+            vec![agent, other, failing],
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        )
+        .unwrap();
+        let mut items: Vec<(String, Result<String>)> = stream.collect().await;
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0], ("other".to_string(), Ok("ok".to_string())));
+        assert_eq!(items[1].0, "test");
+        assert!(items
+            .iter()
+            .any(|(id, chunk)| id == "test" && chunk.is_err()));
+        assert!(execute_parallel_stream(vec![], vec!["x".to_string()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retry_budget_caps_retries_across_the_batch() {
+        let (mut agent, backend) = mock_agent(MockBackend::with_reply(MockReply::ConnectionError(
+            "refused".to_string(),
+        )));
+        agent.config.max_retries = 5;
+        agent.config.retry_backoff_ms = 1;
+
+        let results = execute_parallel_with_retry_budget(
+            vec![agent.clone(), agent.clone(), agent],
+            vec!["a".to_string(); 3],
+            2,
+        )
+        .await
+        .unwrap();
+        assert!(results.iter().all(|r| r.status == AgentStatus::Failed));
+        assert_eq!(backend.requests().len(), 3 + 2);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_after_repeated_failures() {
+        let (mut agent, backend) = mock_agent(MockBackend::with_reply(MockReply::ConnectionError(
+            "refused".to_string(),
+        )));
+        agent.config.ollama_url = "http://circuit-test:11434".to_string();
+        agent.config.failure_threshold = 2;
+
+        for _ in 0..2 {
+            assert_eq!(
+                agent.execute("a").await.unwrap_err(),
+                AgentError::Connection("refused".to_string())
+            );
+        }
+        let result = agent.execute("a").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Failed);
+        assert_eq!(result.error.as_deref(), Some("circuit open"));
+        assert_eq!(backend.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_timeout_overrides_config() {
+        let (agent, _) =
+            mock_agent(MockBackend::with_response("late").with_delay(Duration::from_millis(200)));
+
+        let result = agent
+            .execute_with_timeout("a", Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(result.status, AgentStatus::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn test_json_format_is_sent_and_validated() {
+        let (mut agent, backend) = mock_agent(MockBackend::with_response("not json"));
+        agent.config.format = OutputFormat::Json;
+        agent.config.validate_json = true;
+
+        let result = agent.execute("a").await.unwrap();
+        assert_eq!(backend.requests()[0].body["format"], "json");
+        assert_eq!(result.status, AgentStatus::Failed);
+        assert!(result.error.unwrap().contains("not valid JSON"));
+    }
+
+    struct Guardrail;
+
+    #[async_trait::async_trait]
+    impl Middleware for Guardrail {
+        async fn before(&self, request: &mut LlmRequest) {
+            let prompt = request.body["prompt"].as_str().unwrap_or_default();
+            request.body["prompt"] = format!("Be safe. {prompt}").into();
+        }
+
+        async fn after(&self, result: &mut AgentResult) {
+            result.output = result
+                .output
+                .take()
+                .map(|o| o.replace("hunter2", "[redacted]"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_rewrites_request_and_result() {
+        let (agent, backend) = mock_agent(MockBackend::with_response("password is hunter2"));
+        let agent = agent.with_middleware(Guardrail);
+
+        let result = agent.execute("hi").await.unwrap();
+        assert_eq!(backend.requests()[0].body["prompt"], "Be safe. hi");
+        assert_eq!(result.output.as_deref(), Some("password is [redacted]"));
+    }
+
+    struct StatusTag;
+
+    #[async_trait::async_trait]
+    impl Middleware for StatusTag {
+        async fn after(&self, result: &mut AgentResult) {
+            result
+                .metadata
+                .insert("seen".to_string(), result.status.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_after_sees_timed_out_results() {
+        let (agent, backend) = mock_agent(MockBackend::with_response("ok"));
+        let agent = agent.with_middleware(StatusTag);
+        backend.push(MockReply::Timeout);
+
+        let result = agent.execute("hi").await.unwrap();
+        assert_eq!(result.status, AgentStatus::TimedOut);
+        assert_eq!(result.metadata["seen"], "timed_out");
+    }
+
+    #[tokio::test]
+    async fn test_max_response_bytes_fails_oversized_reply() {
+        let (mut agent, _backend) = mock_agent(MockBackend::with_response(&"x".repeat(1000)));
+        agent.config.max_response_bytes = Some(100);
+
+        let result = agent.execute("hi").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Failed);
+        assert!(result.error.unwrap().contains("response too large"));
+
+        let streamed = agent.execute_streamed("hi").await.unwrap();
+        assert_eq!(streamed.status, AgentStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_stream_with_progress_reports_rate() {
+        let (agent, backend) = mock_agent(MockBackend::with_response("unused"));
+        backend.push(MockReply::Response(LlmResponse {
+            status: 200,
+            content_type: Some("application/x-ndjson".to_string()),
+            body:
+                b"{\"response\":\"a\"}\n{\"response\":\"b\"}\n{\"response\":\"\",\"done\":true}\n"
+                    .to_vec(),
+        }));
+
+        let events: Vec<StreamEvent> = agent
+            .execute_stream_with_progress("hi", Duration::ZERO)
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+        let progress: Vec<u64> = events
+            .iter()
+            .filter_map(|event| match event {
+                StreamEvent::Progress(progress) => Some(progress.tokens_so_far),
+                StreamEvent::Chunk(_) => None,
+            })
+            .collect();
+        assert_eq!(events[0], StreamEvent::Chunk("a".to_string()));
+        assert_eq!(progress, [1, 2]);
     }
 }
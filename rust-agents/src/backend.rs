@@ -0,0 +1,562 @@
+// Transport abstraction for talking to a model server, plus load balancing
+// across several Ollama instances
+
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use tokio::task::JoinSet;
+
+use crate::{
+    agent_runtime::{check_lengths, collect_completed, AgentResult, AgentStatus, RustAgent},
+    error::{AgentError, Result},
+    telemetry,
+};
+
+/// A request for a model server: `body` is POSTed as JSON to
+/// `base_url` + `path`.
+#[derive(Debug, Clone)]
+pub struct LlmRequest {
+    pub base_url: String,
+    pub path: String,
+    pub body: serde_json::Value,
+    pub timeout: Duration,
+    /// Extra headers, including any `Authorization`
+    pub headers: Vec<(String, String)>,
+    /// Largest reply body to read, cumulative for streams; a longer one
+    /// fails with [`AgentError::ResponseTooLarge`]
+    pub max_response_bytes: Option<usize>,
+}
+
+impl LlmRequest {
+    pub fn url(&self) -> String {
+        format!("{}{}", self.base_url, self.path)
+    }
+}
+
+/// A fully-read response from a model server.
+#[derive(Debug, Clone)]
+pub struct LlmResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl LlmResponse {
+    /// A `200 OK` response carrying `body` as JSON.
+    pub fn json_ok(body: &serde_json::Value) -> Self {
+        Self {
+            status: 200,
+            content_type: Some("application/json".to_string()),
+            body: body.to_string().into_bytes(),
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.status)
+    }
+
+    pub fn json(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// Raw response body delivered incrementally.
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>;
+
+/// How a [`RustAgent`] reaches its model. Implementations report failures
+/// below the HTTP layer as [`AgentError::Timeout`] or
+/// [`AgentError::Connection`] so the runtime can retry them. [`HttpBackend`]
+/// is the real implementation; [`crate::mock::MockBackend`] serves canned
+/// replies for tests.
+#[async_trait]
+pub trait LlmBackend: Send + Sync {
+    async fn generate(&self, request: LlmRequest) -> Result<LlmResponse>;
+
+    /// Streaming variant used by `execute_stream`. Non-2xx responses are an
+    /// error. The default buffers the whole reply from `generate` and yields
+    /// it as a single chunk.
+    async fn generate_stream(&self, request: LlmRequest) -> Result<ByteStream> {
+        let response = self.generate(request).await?;
+        if !response.is_success() {
+            return Err(AgentError::HttpStatus(response.status));
+        }
+        Ok(Box::pin(futures::stream::once(
+            async move { Ok(response.body) },
+        )))
+    }
+}
+
+/// [`LlmBackend`] that POSTs over HTTP with a `reqwest::Client`.
+#[derive(Clone)]
+pub struct HttpBackend {
+    client: reqwest::Client,
+}
+
+impl HttpBackend {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    async fn send(&self, request: &LlmRequest) -> Result<reqwest::Response> {
+        let mut builder = self.client.post(request.url()).timeout(request.timeout);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        builder
+            .json(&request.body)
+            .send()
+            .await
+            .map_err(|err| send_error(err, &request.base_url))
+    }
+}
+
+// Like `transport_error`, but a failure to connect at all (refused, DNS)
+// says which server was unreachable
+pub(crate) fn send_error(err: reqwest::Error, base_url: &str) -> AgentError {
+    if err.is_connect() {
+        AgentError::Connection(format!(
+            "could not reach Ollama at {base_url}; is the server running? ({err})"
+        ))
+    } else {
+        transport_error(err)
+    }
+}
+
+pub(crate) fn transport_error(err: reqwest::Error) -> AgentError {
+    if err.is_timeout() {
+        AgentError::Timeout
+    } else if err.is_decode() {
+        AgentError::Deserialize(err.to_string())
+    } else if err.is_builder() {
+        AgentError::Config(err.to_string())
+    } else {
+        AgentError::Connection(err.to_string())
+    }
+}
+
+#[async_trait]
+impl LlmBackend for HttpBackend {
+    async fn generate(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let response = self.send(&request).await?;
+        let status = response.status().as_u16();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        let body = read_body(response, request.max_response_bytes).await?;
+
+        Ok(LlmResponse {
+            status,
+            content_type,
+            body,
+        })
+    }
+
+    async fn generate_stream(&self, request: LlmRequest) -> Result<ByteStream> {
+        let response = self.send(&request).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(AgentError::HttpStatus(status.as_u16()));
+        }
+
+        let limit = request.max_response_bytes;
+        let mut received = 0;
+        Ok(Box::pin(response.bytes_stream().map(move |chunk| {
+            let bytes = chunk.map_err(transport_error)?;
+            received += bytes.len();
+            match limit {
+                Some(limit) if received > limit => Err(AgentError::ResponseTooLarge(limit)),
+                _ => Ok(bytes.to_vec()),
+            }
+        })))
+    }
+}
+
+// Reads the whole body, giving up as soon as it exceeds `limit` rather than
+// buffering an unbounded reply first
+async fn read_body(mut response: reqwest::Response, limit: Option<usize>) -> Result<Vec<u8>> {
+    let Some(limit) = limit else {
+        return Ok(response.bytes().await.map_err(transport_error)?.to_vec());
+    };
+    if response
+        .content_length()
+        .is_some_and(|len| len > limit as u64)
+    {
+        return Err(AgentError::ResponseTooLarge(limit));
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(transport_error)? {
+        if body.len() + chunk.len() > limit {
+            return Err(AgentError::ResponseTooLarge(limit));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+// Weight of the newest sample in the latency EMA
+const EMA_ALPHA: f64 = 0.2;
+
+// Folds `sample` into a latency EMA; the first sample starts it. Shared with
+// the adaptive limiter so both smooth latency the same way.
+pub(crate) fn ema(previous: Option<f64>, sample: f64) -> f64 {
+    match previous {
+        Some(ema) => EMA_ALPHA * sample + (1.0 - EMA_ALPHA) * ema,
+        None => sample,
+    }
+}
+
+/// Rolling latency estimate for one backend URL.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BackendStats {
+    pub url: String,
+    /// Exponential moving average of `duration_ms`; `None` until the first
+    /// sample
+    pub ema_latency_ms: Option<f64>,
+    pub samples: u64,
+}
+
+impl BackendStats {
+    fn record(&mut self, duration_ms: f64) {
+        self.ema_latency_ms = Some(ema(self.ema_latency_ms, duration_ms));
+        self.samples += 1;
+    }
+}
+
+/// A set of Ollama base URLs that agents are spread across. Each request
+/// goes to the healthy backend with the lowest expected wait, its latency
+/// EMA times one more than its in-flight requests; backends without samples
+/// yet are tried first and ties go round-robin. A backend that fails at the
+/// transport level, times out, or answers with a 5xx is skipped for a
+/// cooldown period; if every backend is cooling down, plain round-robin
+/// resumes.
+pub struct Backend {
+    urls: Vec<String>,
+    next: AtomicUsize,
+    down_until: Mutex<Vec<Option<Instant>>>,
+    cooldown: Duration,
+    latency: Mutex<Vec<BackendStats>>,
+    in_flight: Vec<AtomicUsize>,
+}
+
+impl Backend {
+    pub fn new(urls: Vec<String>) -> Result<Self> {
+        if urls.is_empty() {
+            return Err(AgentError::Config(
+                "backend requires at least one ollama_url".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            down_until: Mutex::new(vec![None; urls.len()]),
+            latency: Mutex::new(
+                urls.iter()
+                    .map(|url| BackendStats {
+                        url: url.clone(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            ),
+            in_flight: urls.iter().map(|_| AtomicUsize::new(0)).collect(),
+            urls,
+            next: AtomicUsize::new(0),
+            cooldown: DEFAULT_COOLDOWN,
+        })
+    }
+
+    /// How long a failed backend is skipped before being tried again.
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    /// The healthy URL with the lowest expected wait.
+    pub fn next_url(&self) -> &str {
+        &self.urls[self.next_index()]
+    }
+
+    fn next_index(&self) -> usize {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let n = self.urls.len();
+        let now = Instant::now();
+        let down_until = self.down_until.lock().unwrap_or_else(|e| e.into_inner());
+        let latency = self.latency.lock().unwrap_or_else(|e| e.into_inner());
+        let expected_wait = |i: usize| {
+            let queued = self.in_flight[i].load(Ordering::Relaxed) + 1;
+            latency[i].ema_latency_ms.unwrap_or(0.0) * queued as f64
+        };
+
+        (0..n)
+            .map(|offset| (start + offset) % n)
+            .filter(|&i| down_until[i].is_none_or(|until| until <= now))
+            .reduce(|best, i| {
+                if expected_wait(i) < expected_wait(best) {
+                    i
+                } else {
+                    best
+                }
+            })
+            .unwrap_or(start % n)
+    }
+
+    /// Latency estimates for every backend, in the order of `urls`.
+    pub fn stats(&self) -> Vec<BackendStats> {
+        self.latency
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Folds one observed request duration for `url` into its latency EMA.
+    /// Called by [`Backend::execute`]; exposed for callers that dispatch
+    /// requests themselves.
+    pub fn record_latency(&self, url: &str, duration_ms: f64) {
+        if let Some(i) = self.position(url) {
+            self.latency.lock().unwrap_or_else(|e| e.into_inner())[i].record(duration_ms);
+        }
+    }
+
+    pub fn mark_failed(&self, url: &str) {
+        self.set_down_until(url, Some(Instant::now() + self.cooldown));
+    }
+
+    pub fn mark_healthy(&self, url: &str) {
+        self.set_down_until(url, None);
+    }
+
+    fn set_down_until(&self, url: &str, until: Option<Instant>) {
+        if let Some(i) = self.position(url) {
+            self.down_until.lock().unwrap_or_else(|e| e.into_inner())[i] = until;
+        }
+    }
+
+    fn position(&self, url: &str) -> Option<usize> {
+        self.urls.iter().position(|u| u == url)
+    }
+
+    /// Runs `agent` against the next healthy backend, failing over to the
+    /// others when a request fails at the transport level. A `TimedOut`
+    /// result, or a `Failed` one caused by a 5xx response, is returned as-is,
+    /// but its backend is put in cooldown like a transport failure. Failures
+    /// caused by the request itself (a prompt that is too long, invalid
+    /// output, a 4xx response) leave the backend in rotation, as do other
+    /// errors, which are returned without failing over. The agent's own
+    /// `ollama_url` is ignored.
+    pub async fn execute(&self, agent: &RustAgent, task: &str) -> Result<AgentResult> {
+        let mut last_err = None;
+
+        for _ in 0..self.urls.len() {
+            let i = self.next_index();
+            let url = self.urls[i].clone();
+            let outcome = {
+                let _in_flight = InFlight::enter(&self.in_flight[i]);
+                agent.retargeted(&url).execute(task).await
+            };
+
+            match outcome {
+                Ok(result) => {
+                    match result.status {
+                        AgentStatus::Completed => self.mark_healthy(&url),
+                        AgentStatus::TimedOut => self.mark_failed(&url),
+                        AgentStatus::Failed if is_server_failure(&result) => self.mark_failed(&url),
+                        // The request's own fault (too long, invalid output,
+                        // a 4xx, an open circuit) or a cancellation: says
+                        // nothing about the backend
+                        AgentStatus::Failed | AgentStatus::Cancelled => return Ok(result),
+                    }
+                    self.record_latency(&url, result.duration_ms as f64);
+                    return Ok(result);
+                },
+                Err(e) if e.is_retryable() => {
+                    self.mark_failed(&url);
+                    last_err = Some(e);
+                },
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| AgentError::Config("no backends available".to_string())))
+    }
+}
+
+// Whether a `Failed` result came from a 5xx response, as reported by
+// `RustAgent::execute` ("HTTP 503: ...")
+fn is_server_failure(result: &AgentResult) -> bool {
+    result
+        .error
+        .as_deref()
+        .and_then(|error| error.strip_prefix("HTTP "))
+        .and_then(|rest| rest.get(..3))
+        .and_then(|status| status.parse::<u16>().ok())
+        .is_some_and(|status| status >= 500)
+}
+
+// Counts a request against its backend until dropped, so cancelled requests
+// are released too
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl<'a> InFlight<'a> {
+    fn enter(count: &'a AtomicUsize) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        Self(count)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Same as [`crate::execute_parallel`], but each agent is sent to one of
+/// `urls` instead of its configured `ollama_url`, picked by [`Backend`]: the
+/// healthy URL with the lowest latency EMA weighted by its in-flight
+/// requests. An agent that fails on every backend yields a `Failed` result.
+pub async fn execute_parallel_balanced(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+    urls: Vec<String>,
+) -> Result<Vec<AgentResult>> {
+    check_lengths(agents.len(), tasks.len())?;
+
+    let backend = Arc::new(Backend::new(urls)?);
+    let mut set = JoinSet::new();
+
+    for (index, (agent, task)) in agents.into_iter().zip(tasks).enumerate() {
+        let backend = Arc::clone(&backend);
+        set.spawn(telemetry::in_current_span(async move {
+            let start = std::time::Instant::now();
+            let outcome = backend.execute(&agent, &task).await;
+            agent.settle(outcome, start).for_task(index)
+        }));
+    }
+
+    Ok(collect_completed(set).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_url_skips_failed_backends() {
+        let backend = Backend::new(vec!["http://a".to_string(), "http://b".to_string()]).unwrap();
+        assert_eq!(backend.next_url(), "http://a");
+        assert_eq!(backend.next_url(), "http://b");
+
+        backend.mark_failed("http://a");
+        assert_eq!(backend.next_url(), "http://b");
+        assert_eq!(backend.next_url(), "http://b");
+
+        backend.mark_healthy("http://a");
+        assert_eq!(backend.next_url(), "http://a");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server_names_the_url() {
+        let backend = HttpBackend::new(reqwest::Client::new());
+        let err = backend
+            .generate(LlmRequest {
+                base_url: "http://127.0.0.1:9".to_string(),
+                path: "/api/generate".to_string(),
+                body: serde_json::json!({}),
+                timeout: Duration::from_secs(5),
+                headers: Vec::new(),
+                max_response_bytes: None,
+            })
+            .await
+            .unwrap_err();
+
+        assert!(err.is_retryable());
+        assert!(err
+            .to_string()
+            .contains("could not reach Ollama at http://127.0.0.1:9; is the server running?"));
+    }
+
+    #[test]
+    fn test_latency_ema_converges_and_steers_selection() {
+        let backend = Backend::new(vec!["http://a".to_string(), "http://b".to_string()]).unwrap();
+
+        backend.record_latency("http://a", 100.0);
+        assert_eq!(backend.stats()[0].ema_latency_ms, Some(100.0));
+        for _ in 0..50 {
+            backend.record_latency("http://a", 500.0);
+            backend.record_latency("http://b", 50.0);
+        }
+
+        let stats = backend.stats();
+        assert_eq!(stats[0].samples, 51);
+        assert!((stats[0].ema_latency_ms.unwrap() - 500.0).abs() < 0.01);
+        assert!((stats[1].ema_latency_ms.unwrap() - 50.0).abs() < 0.01);
+
+        assert_eq!(backend.next_url(), "http://b");
+        assert_eq!(backend.next_url(), "http://b");
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_results_put_the_backend_in_cooldown() {
+        let backend = Backend::new(vec!["http://a".to_string(), "http://b".to_string()]).unwrap();
+        let mock = Arc::new(crate::MockBackend::with_response("ok"));
+        mock.push(crate::MockReply::Timeout);
+        let agent = RustAgent::with_backend(crate::AgentConfig::default(), mock);
+
+        let result = backend.execute(&agent, "a").await.unwrap();
+        assert_eq!(result.status, AgentStatus::TimedOut);
+        assert_eq!(backend.next_url(), "http://b");
+        assert_eq!(backend.next_url(), "http://b");
+
+        let backend = Backend::new(vec!["http://a".to_string(), "http://b".to_string()]).unwrap();
+        let mock = Arc::new(crate::MockBackend::with_response("ok"));
+        mock.push_json(503, serde_json::json!({"error": "overloaded"}));
+        let agent = RustAgent::with_backend(crate::AgentConfig::default(), mock);
+
+        let result = backend.execute(&agent, "a").await.unwrap();
+        assert_eq!(result.status, AgentStatus::Failed);
+        assert_eq!(backend.next_url(), "http://b");
+        assert_eq!(backend.next_url(), "http://b");
+    }
+
+    #[tokio::test]
+    async fn test_request_failures_leave_the_backend_selectable() {
+        let backend = Backend::new(vec!["http://a".to_string(), "http://b".to_string()]).unwrap();
+        let mock = Arc::new(crate::MockBackend::with_response("not json"));
+        mock.push_json(400, serde_json::json!({"error": "invalid options"}));
+        let config = crate::AgentConfig {
+            validate_json: true,
+            ..Default::default()
+        };
+        let agent = RustAgent::with_backend(config, mock);
+
+        for _ in 0..2 {
+            let result = backend.execute(&agent, "a").await.unwrap();
+            assert_eq!(result.status, AgentStatus::Failed);
+        }
+        let urls = [backend.next_url(), backend.next_url()];
+        assert!(urls.contains(&"http://a"), "{urls:?}");
+        assert!(backend.stats().iter().all(|s| s.samples == 0));
+    }
+}
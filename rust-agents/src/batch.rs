@@ -0,0 +1,212 @@
+// Running batches that can be cancelled one agent at a time or all at once
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::task::{Id, JoinHandle, JoinSet};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{
+    agent_runtime::{
+        check_lengths, execute_parallel_with_cancel, join_failure, AgentResult, RustAgent,
+    },
+    error::{AgentError, Result},
+    telemetry,
+};
+
+// Tokens of the agents still running; each task removes its own entry when it
+// finishes so cancelling a finished agent is reported as a no-op
+type Registry = Arc<Mutex<HashMap<Uuid, CancellationToken>>>;
+
+/// Agents started by [`spawn_parallel`]. Every agent is assigned its
+/// `run_id` up front, so one can be cancelled while the rest keep running;
+/// the cancelled agent's result comes back with status `Cancelled`.
+pub struct AgentBatch {
+    run_ids: Vec<Uuid>,
+    tokens: Registry,
+    set: JoinSet<AgentResult>,
+    ids: HashMap<Id, Uuid>,
+}
+
+/// Same as [`crate::execute_parallel`], but returns immediately with a handle
+/// to the running batch instead of waiting for it. Must be called from
+/// within a tokio runtime.
+pub fn spawn_parallel(agents: Vec<RustAgent>, tasks: Vec<String>) -> Result<AgentBatch> {
+    check_lengths(agents.len(), tasks.len())?;
+    let tokens: Registry = Arc::default();
+    let mut set = JoinSet::new();
+    let mut run_ids = Vec::new();
+    let mut ids = HashMap::new();
+
+    for (index, (agent, task)) in agents.into_iter().zip(tasks).enumerate() {
+        let run_id = Uuid::new_v4();
+        let cancel = CancellationToken::new();
+        lock(&tokens).insert(run_id, cancel.clone());
+
+        let tokens = Arc::clone(&tokens);
+        let handle = set.spawn(telemetry::in_current_span(async move {
+            let start = std::time::Instant::now();
+            let outcome = agent.execute_with_cancel(&task, &cancel).await;
+            lock(&tokens).remove(&run_id);
+            AgentResult {
+                run_id,
+                ..agent.settle(outcome, start).for_task(index)
+            }
+        }));
+        ids.insert(handle.id(), run_id);
+        run_ids.push(run_id);
+    }
+
+    Ok(AgentBatch {
+        run_ids,
+        tokens,
+        set,
+        ids,
+    })
+}
+
+impl AgentBatch {
+    /// The run id of each agent, in input order.
+    pub fn run_ids(&self) -> &[Uuid] {
+        &self.run_ids
+    }
+
+    /// Cancels the agent running as `run_id`. Returns `false` when no such
+    /// agent is still running.
+    pub fn cancel(&self, run_id: Uuid) -> bool {
+        match lock(&self.tokens).remove(&run_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Cancels every agent that is still running.
+    pub fn cancel_all(&self) {
+        for (_, token) in lock(&self.tokens).drain() {
+            token.cancel();
+        }
+    }
+
+    /// Waits for every agent and returns their results in completion order,
+    /// one per input.
+    pub async fn join(mut self) -> Vec<AgentResult> {
+        let mut results = Vec::with_capacity(self.set.len());
+        while let Some(res) = self.set.join_next_with_id().await {
+            let result = match res {
+                Ok((_, result)) => result,
+                Err(e) => {
+                    let run_id = self.ids.get(&e.id()).copied();
+                    let failure = join_failure(e);
+                    AgentResult {
+                        run_id: run_id.unwrap_or(failure.run_id),
+                        ..failure
+                    }
+                },
+            };
+            results.push(result);
+        }
+        results
+    }
+}
+
+/// A batch started by [`spawn_batch`], cancelled as a whole. Unlike
+/// [`AgentBatch`] there is one token for every agent, which can be handed to
+/// other tasks (e.g. a UI's stop button) with [`BatchHandle::cancel_token`].
+pub struct BatchHandle {
+    cancel: CancellationToken,
+    results: JoinHandle<Result<Vec<AgentResult>>>,
+}
+
+/// Same as [`crate::execute_parallel_with_cancel`], but returns immediately
+/// with a handle to cancel or await the running batch. Must be called from
+/// within a tokio runtime.
+pub fn spawn_batch(agents: Vec<RustAgent>, tasks: Vec<String>) -> BatchHandle {
+    let cancel = CancellationToken::new();
+    let results = tokio::spawn(telemetry::in_current_span(execute_parallel_with_cancel(
+        agents,
+        tasks,
+        cancel.clone(),
+    )));
+    BatchHandle { cancel, results }
+}
+
+impl BatchHandle {
+    /// Cancels every agent still running; their results come back with
+    /// status `Cancelled`.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// The token shared by every agent in the batch; cancelling it is the
+    /// same as [`BatchHandle::cancel`].
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Waits for the batch and returns its results in completion order.
+    pub async fn await_results(self) -> Result<Vec<AgentResult>> {
+        self.results
+            .await
+            .map_err(|e| AgentError::Backend(format!("batch task failed: {e}")))?
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{agent_runtime::AgentStatus, mock::MockBackend, AgentConfig};
+
+    #[tokio::test]
+    async fn test_cancel_one_agent_leaves_the_rest_running() {
+        let backend =
+            Arc::new(MockBackend::with_response("ok").with_delay(Duration::from_millis(50)));
+        let agents = (0..3)
+            .map(|_| RustAgent::with_backend(AgentConfig::default(), backend.clone()))
+            .collect();
+        let tasks = ["a", "b", "c"].map(String::from).to_vec();
+
+        let batch = spawn_parallel(agents, tasks).unwrap();
+        let target = batch.run_ids()[1];
+        assert!(batch.cancel(target));
+        assert!(!batch.cancel(target), "already cancelled");
+
+        let results = batch.join().await;
+        assert_eq!(results.len(), 3);
+        for result in results {
+            let expected = if result.run_id == target {
+                AgentStatus::Cancelled
+            } else {
+                AgentStatus::Completed
+            };
+            assert_eq!(result.status, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_handle_cancels_from_another_task() {
+        let backend = Arc::new(MockBackend::with_response("ok").with_delay(Duration::from_secs(5)));
+        let agents = (0..2)
+            .map(|_| RustAgent::with_backend(AgentConfig::default(), backend.clone()))
+            .collect();
+
+        let handle = spawn_batch(agents, vec!["a".to_string(), "b".to_string()]);
+        let stop = handle.cancel_token();
+        tokio::spawn(async move { stop.cancel() });
+
+        let results = handle.await_results().await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.status == AgentStatus::Cancelled));
+    }
+}
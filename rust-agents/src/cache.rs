@@ -0,0 +1,134 @@
+// In-memory response cache for repeated identical requests
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use crate::agent_runtime::{AgentConfig, AgentResult};
+
+struct Entry {
+    result: AgentResult,
+    inserted: Instant,
+    last_used: u64,
+}
+
+/// Least-recently-used cache of completed results keyed by the agent's
+/// [`AgentConfig::fingerprint`] of the request. Eviction scans for the
+/// oldest entry, which is fine at the capacities this is meant for
+/// (development re-runs, not serving).
+pub(crate) struct ResponseCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<u64, Entry>,
+    clock: u64,
+}
+
+impl ResponseCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// The cached result for `key`, unless it is older than `ttl`.
+    pub(crate) fn get(&self, key: u64, ttl: Option<Duration>) -> Option<AgentResult> {
+        let mut state = self.lock();
+        state.clock += 1;
+        let clock = state.clock;
+
+        let fresh = state
+            .entries
+            .get(&key)
+            .map(|entry| ttl.is_none_or(|ttl| entry.inserted.elapsed() < ttl))?;
+        if !fresh {
+            state.entries.remove(&key);
+            return None;
+        }
+        let entry = state.entries.get_mut(&key)?;
+        entry.last_used = clock;
+        Some(entry.result.clone())
+    }
+
+    pub(crate) fn insert(&self, key: u64, result: AgentResult) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = self.lock();
+        state.clock += 1;
+        let clock = state.clock;
+
+        if state.entries.len() >= self.capacity && !state.entries.contains_key(&key) {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                state.entries.remove(&oldest);
+            }
+        }
+        state.entries.insert(
+            key,
+            Entry {
+                result,
+                inserted: Instant::now(),
+                last_used: clock,
+            },
+        );
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Key for a request: `config`'s fingerprint over the path and the full body,
+/// which covers chat messages and images as well as plain prompts. The
+/// fingerprint adds the server and the credentials the request is sent with,
+/// and the settings that decide what a reply turns into; `output_pointer`
+/// is where the output is read from in the reply.
+pub(crate) fn cache_key(
+    config: &AgentConfig,
+    path: &str,
+    body: &serde_json::Value,
+    output_pointer: &str,
+) -> u64 {
+    config.result_fingerprint(&format!("{path} {output_pointer} {body}"))
+}
+
+// Process-wide, so results survive across agents built for each call (as the
+// Python bindings do)
+static CACHE: OnceLock<ResponseCache> = OnceLock::new();
+
+/// The shared cache. The first caller's capacity wins; see
+/// [`AgentConfig::cache_capacity`].
+pub(crate) fn shared(capacity: usize) -> &'static ResponseCache {
+    CACHE.get_or_init(|| ResponseCache::new(capacity))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_evicts_least_recently_used_and_expires() {
+        let cache = ResponseCache::new(2);
+        cache.insert(1, AgentResult::completed("test", "one"));
+        cache.insert(2, AgentResult::completed("test", "two"));
+        assert!(cache.get(1, None).is_some());
+
+        cache.insert(3, AgentResult::completed("test", "three"));
+        assert!(cache.get(2, None).is_none(), "2 was least recently used");
+        assert!(cache.get(1, None).is_some());
+
+        assert!(cache.get(3, Some(Duration::ZERO)).is_none());
+        assert!(cache.get(3, None).is_none(), "expired entries are dropped");
+    }
+}
@@ -0,0 +1,212 @@
+// Resumable batches: results are appended to a JSON-lines checkpoint as they
+// finish so a restarted batch can skip work that already succeeded
+
+use std::{collections::HashMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, task::JoinSet};
+
+use crate::{
+    agent_runtime::{check_lengths, join_failure, AgentResult, AgentStatus, RustAgent},
+    error::{AgentError, Result},
+    telemetry,
+};
+
+// One line of the checkpoint file. `task` is stored so a checkpoint written
+// for a different batch is detected instead of silently reused.
+#[derive(Serialize, Deserialize)]
+struct CheckpointEntry {
+    index: usize,
+    task: String,
+    result: AgentResult,
+}
+
+/// Same as [`crate::execute_parallel_ordered`], but every result is appended
+/// to the JSON-lines file at `checkpoint_path` as soon as it finishes. When the
+/// file already exists, tasks it records as `Completed` are not run again and
+/// their recorded results are returned in their place; failed, timed-out, or
+/// missing tasks are run. A partially written last line (e.g. from a crash)
+/// is discarded; any other line that doesn't parse fails with
+/// [`AgentError::Deserialize`] rather than silently rerunning its task.
+pub async fn execute_parallel_checkpointed(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+    checkpoint_path: impl AsRef<Path>,
+) -> Result<Vec<AgentResult>> {
+    check_lengths(agents.len(), tasks.len())?;
+    let path = checkpoint_path.as_ref();
+    let (mut done, valid_len) = read_checkpoint(path, &tasks).await?;
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .map_err(|e| io_error(path, e))?;
+    // Appending after a torn line would corrupt the next entry too
+    file.set_len(valid_len)
+        .await
+        .map_err(|e| io_error(path, e))?;
+
+    let mut set = JoinSet::new();
+    let mut indices = HashMap::new();
+    for (index, (agent, task)) in agents.into_iter().zip(tasks).enumerate() {
+        if done.contains_key(&index) {
+            continue;
+        }
+        let handle = set.spawn(telemetry::in_current_span(async move {
+            let result = agent.execute_settled(&task).await;
+            CheckpointEntry {
+                index,
+                task,
+                result,
+            }
+        }));
+        indices.insert(handle.id(), index);
+    }
+
+    while let Some(res) = set.join_next_with_id().await {
+        let entry = match res {
+            Ok((_, entry)) => entry,
+            // A panicked task leaves nothing worth recording; it is simply
+            // run again on the next attempt.
+            Err(e) => {
+                done.insert(indices[&e.id()], join_failure(e));
+                continue;
+            },
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        file.write_all(&line).await.map_err(|e| io_error(path, e))?;
+        file.flush().await.map_err(|e| io_error(path, e))?;
+        done.insert(entry.index, entry.result);
+    }
+
+    let mut results: Vec<(usize, AgentResult)> = done.into_iter().collect();
+    results.sort_by_key(|(index, _)| *index);
+    Ok(results
+        .into_iter()
+        .map(|(index, result)| result.for_task(index))
+        .collect())
+}
+
+// Completed results recorded for this batch, keyed by task index, and the
+// length of the file up to its last complete line
+async fn read_checkpoint(
+    path: &Path,
+    tasks: &[String],
+) -> Result<(HashMap<usize, AgentResult>, u64)> {
+    let contents = match tokio::fs::read_to_string(path).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((HashMap::new(), 0)),
+        Err(e) => return Err(io_error(path, e)),
+    };
+
+    let mut done = HashMap::new();
+    let mut valid_len = 0;
+    for (number, line) in contents.split_inclusive('\n').enumerate() {
+        // Every entry is written with its newline, so a line without one is
+        // what a crash mid-write leaves behind
+        if !line.ends_with('\n') {
+            break;
+        }
+        valid_len += line.len() as u64;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str::<CheckpointEntry>(line).map_err(|e| {
+            AgentError::Deserialize(format!(
+                "{}: corrupt checkpoint line {}: {e}",
+                path.display(),
+                number + 1
+            ))
+        })?;
+        if tasks.get(entry.index) != Some(&entry.task) {
+            return Err(AgentError::Config(format!(
+                "{}: checkpoint entry {} does not match this batch's tasks",
+                path.display(),
+                entry.index
+            )));
+        }
+        // Later lines win, so a retried task replaces its earlier failure
+        if entry.result.status == AgentStatus::Completed {
+            done.insert(entry.index, entry.result);
+        } else {
+            done.remove(&entry.index);
+        }
+    }
+    Ok((done, valid_len))
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> AgentError {
+    AgentError::Io(format!("{}: {err}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{agent_runtime::AgentConfig, mock::MockBackend};
+
+    #[tokio::test]
+    async fn test_checkpoint_resume_skips_completed_tasks() {
+        let path = std::env::temp_dir().join(format!("checkpoint-{}.jsonl", uuid::Uuid::new_v4()));
+        let backend = Arc::new(MockBackend::with_response("ok"));
+        let config = AgentConfig {
+            name: "test".to_string(),
+            model: "qwen2.5-coder:14b".to_string(),
+            ..Default::default()
+        };
+        let agent = RustAgent::with_backend(config, backend.clone());
+        let tasks = vec!["a".to_string(), "b".to_string()];
+
+        backend.push_json(500, serde_json::json!({"error": "boom"}));
+        let first =
+            execute_parallel_checkpointed(vec![agent.clone(), agent.clone()], tasks.clone(), &path)
+                .await
+                .unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(backend.requests().len(), 2);
+
+        // Only the failed task runs again
+        let second = execute_parallel_checkpointed(vec![agent.clone(), agent], tasks, &path)
+            .await
+            .unwrap();
+        assert!(second.iter().all(|r| r.status == AgentStatus::Completed));
+        assert_eq!(backend.requests().len(), 3);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_drops_a_torn_last_line_but_rejects_corruption() {
+        let path = std::env::temp_dir().join(format!("checkpoint-{}.jsonl", uuid::Uuid::new_v4()));
+        let backend = Arc::new(MockBackend::with_response("ok"));
+        let agent = RustAgent::with_backend(AgentConfig::default(), backend.clone());
+        let tasks = vec!["a".to_string(), "b".to_string()];
+
+        execute_parallel_checkpointed(vec![agent.clone(), agent.clone()], tasks.clone(), &path)
+            .await
+            .unwrap();
+        let mut contents = std::fs::read_to_string(&path).unwrap();
+        let first_line = contents.split_inclusive('\n').next().unwrap().to_string();
+        std::fs::write(&path, format!("{first_line}{{\"index\": 1, \"ta")).unwrap();
+
+        // The torn entry reruns and the file is whole again
+        execute_parallel_checkpointed(vec![agent.clone(), agent.clone()], tasks.clone(), &path)
+            .await
+            .unwrap();
+        assert_eq!(backend.requests().len(), 3);
+        contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 2);
+
+        std::fs::write(&path, format!("not json\n{contents}")).unwrap();
+        let err = execute_parallel_checkpointed(vec![agent.clone(), agent], tasks, &path)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AgentError::Deserialize(_)), "{err}");
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+// Per-backend circuit breaker so a dead Ollama fails batches fast
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+/// Longest cooldown `AgentConfig::validate` accepts.
+pub(crate) const MAX_COOLDOWN: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    /// Requests flow; `failures` consecutive failures seen so far
+    Closed {
+        failures: u32,
+        last_failure: Option<Instant>,
+    },
+    /// Requests fail fast until `until`
+    Open { until: Instant },
+    /// One probe request is in flight until it reports back or `expires`;
+    /// everyone else fails fast
+    HalfOpen { expires: Instant },
+}
+
+/// Trips after `threshold` consecutive failures, each within `cooldown` of the
+/// previous one, then rejects requests for `cooldown`. After that a single
+/// probe is let through: success closes the circuit, failure reopens it.
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            state: Mutex::new(State::Closed {
+                failures: 0,
+                last_failure: None,
+            }),
+        }
+    }
+
+    /// Whether a request may be sent now.
+    pub(crate) fn allow(&self) -> bool {
+        let mut state = self.lock();
+        let now = Instant::now();
+        match *state {
+            State::Closed { .. } => true,
+            State::Open { until } | State::HalfOpen { expires: until } if now < until => false,
+            // Cooldown over, or a previous probe never reported back (e.g. it
+            // was cancelled): let this request through as the probe.
+            State::Open { .. } | State::HalfOpen { .. } => {
+                *state = State::HalfOpen {
+                    expires: self.cooldown_from(now),
+                };
+                true
+            },
+        }
+    }
+
+    pub(crate) fn record_success(&self) {
+        *self.lock() = State::Closed {
+            failures: 0,
+            last_failure: None,
+        };
+    }
+
+    pub(crate) fn record_failure(&self) {
+        let mut state = self.lock();
+        let now = Instant::now();
+        let failures = match *state {
+            State::Closed {
+                failures,
+                last_failure: Some(last),
+            } if now.duration_since(last) <= self.cooldown => failures + 1,
+            State::Closed { .. } => 1,
+            State::Open { .. } | State::HalfOpen { .. } => self.threshold,
+        };
+
+        *state = if failures >= self.threshold {
+            State::Open {
+                until: self.cooldown_from(now),
+            }
+        } else {
+            State::Closed {
+                failures,
+                last_failure: Some(now),
+            }
+        };
+    }
+
+    // When a cooldown starting at `now` ends. Saturates at `MAX_COOLDOWN`
+    // rather than overflowing `Instant` on an absurd cooldown.
+    fn cooldown_from(&self, now: Instant) -> Instant {
+        now.checked_add(self.cooldown)
+            .unwrap_or_else(|| now + MAX_COOLDOWN)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// One breaker per `ollama_url`, shared by every agent in the process
+static BREAKERS: OnceLock<Mutex<HashMap<String, Arc<CircuitBreaker>>>> = OnceLock::new();
+
+/// The shared breaker for `url`. The first caller's thresholds win; agents
+/// pointing at the same backend are expected to agree on them.
+pub(crate) fn breaker_for(url: &str, threshold: u32, cooldown: Duration) -> Arc<CircuitBreaker> {
+    let mut breakers = BREAKERS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    breakers
+        .entry(url.to_string())
+        .or_insert_with(|| Arc::new(CircuitBreaker::new(threshold, cooldown)))
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_opens_then_probes_after_cooldown() {
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(20));
+
+        breaker.record_failure();
+        assert!(breaker.allow());
+        breaker.record_failure();
+        assert!(!breaker.allow());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(breaker.allow(), "probe should be let through");
+        assert!(!breaker.allow(), "only one probe at a time");
+
+        breaker.record_success();
+        assert!(breaker.allow());
+    }
+
+    #[test]
+    fn test_huge_cooldown_saturates_instead_of_panicking() {
+        let breaker = CircuitBreaker::new(1, Duration::MAX);
+        breaker.record_failure();
+        assert!(!breaker.allow());
+    }
+}
@@ -0,0 +1,217 @@
+// Loading agent configurations from JSON, YAML, or TOML
+
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    agent_runtime::AgentConfig,
+    error::{AgentError, Result},
+};
+
+// Top-level layout of a multi-agent file: an `agents` array, i.e.
+// `[[agents]]` tables in TOML or an `agents:` list in YAML
+#[derive(Deserialize)]
+struct AgentsFile {
+    agents: Vec<AgentConfig>,
+}
+
+// Each parser goes through serde_path_to_error so a type mismatch reports
+// the dotted path of the field (e.g. `agents[1].temperature`)
+fn parse_json<T: DeserializeOwned>(contents: &str) -> Result<T> {
+    let de = &mut serde_json::Deserializer::from_str(contents);
+    serde_path_to_error::deserialize(de)
+        .map_err(|e| AgentError::Config(format!("{}: {}", e.path(), e.inner())))
+}
+
+#[cfg(feature = "yaml")]
+fn parse_yaml<T: DeserializeOwned>(contents: &str) -> Result<T> {
+    let de = serde_yaml::Deserializer::from_str(contents);
+    serde_path_to_error::deserialize(de)
+        .map_err(|e| AgentError::Config(format!("{}: {}", e.path(), e.inner())))
+}
+
+#[cfg(feature = "toml")]
+fn parse_toml<T: DeserializeOwned>(contents: &str) -> Result<T> {
+    let de = toml::Deserializer::parse(contents).map_err(|e| AgentError::Config(e.to_string()))?;
+    serde_path_to_error::deserialize(de)
+        .map_err(|e| AgentError::Config(format!("{}: {}", e.path(), e.inner())))
+}
+
+// Prefixes a config error with where it came from
+fn context(err: AgentError, what: impl std::fmt::Display) -> AgentError {
+    AgentError::Config(format!("{what}: {err}"))
+}
+
+impl AgentConfig {
+    pub fn from_json_str(contents: &str) -> Result<Self> {
+        let config: Self =
+            parse_json(contents).map_err(|e| context(e, "invalid JSON agent config"))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml_str(contents: &str) -> Result<Self> {
+        let config: Self =
+            parse_yaml(contents).map_err(|e| context(e, "invalid YAML agent config"))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(contents: &str) -> Result<Self> {
+        let config: Self =
+            parse_toml(contents).map_err(|e| context(e, "invalid TOML agent config"))?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl AgentConfig {
+    /// Overwrites fields from the environment when the variables are set:
+    /// `OLLAMA_URL`, `OLLAMA_MODEL`, `OLLAMA_TEMPERATURE`, and
+    /// `OLLAMA_TIMEOUT_SECONDS`. Call this after loading from a file so the
+    /// environment takes precedence over file values.
+    pub fn apply_env_overrides(&mut self) -> Result<()> {
+        self.apply_overrides(|key| std::env::var(key).ok())
+    }
+
+    fn apply_overrides(&mut self, lookup: impl Fn(&str) -> Option<String>) -> Result<()> {
+        if let Some(url) = lookup("OLLAMA_URL") {
+            self.ollama_url = url;
+        }
+        if let Some(model) = lookup("OLLAMA_MODEL") {
+            self.model = model;
+        }
+        if let Some(temperature) = lookup("OLLAMA_TEMPERATURE") {
+            self.temperature = temperature.trim().parse().map_err(|_| {
+                AgentError::Config(format!(
+                    "OLLAMA_TEMPERATURE must be a number, got '{temperature}'"
+                ))
+            })?;
+        }
+        if let Some(timeout) = lookup("OLLAMA_TIMEOUT_SECONDS") {
+            self.timeout_seconds = timeout.trim().parse().map_err(|_| {
+                AgentError::Config(format!(
+                    "OLLAMA_TIMEOUT_SECONDS must be a whole number of seconds, got '{timeout}'"
+                ))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads every agent from the `agents` array in `path`. The format is picked
+/// from the extension: `.json`, `.yaml`/`.yml` (feature `yaml`), or `.toml`
+/// (feature `toml`). Each loaded config is validated.
+pub fn load_agents(path: impl AsRef<Path>) -> Result<Vec<AgentConfig>> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        AgentError::Io(format!(
+            "failed to read agent config file {}: {e}",
+            path.display()
+        ))
+    })?;
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    let file: AgentsFile = match extension.as_str() {
+        "json" => parse_json(&contents),
+        #[cfg(feature = "yaml")]
+        "yaml" | "yml" => parse_yaml(&contents),
+        #[cfg(feature = "toml")]
+        "toml" => parse_toml(&contents),
+        #[cfg(not(feature = "yaml"))]
+        "yaml" | "yml" => Err(AgentError::Config(format!(
+            "{}: loading YAML configs requires the `yaml` feature",
+            path.display()
+        ))),
+        #[cfg(not(feature = "toml"))]
+        "toml" => Err(AgentError::Config(format!(
+            "{}: loading TOML configs requires the `toml` feature",
+            path.display()
+        ))),
+        _ => Err(AgentError::Config(format!(
+            "{}: unrecognized config extension '{extension}'",
+            path.display()
+        ))),
+    }
+    .map_err(|e| context(e, format!("invalid agent config file {}", path.display())))?;
+
+    for config in &file.agents {
+        config
+            .validate()
+            .map_err(|e| context(e, format!("invalid agent config file {}", path.display())))?;
+    }
+
+    Ok(file.agents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json_str_reports_offending_field() {
+        let config = AgentConfig::from_json_str(
+            r#"{"name": "coder", "model": "qwen2.5-coder:14b",
+                "ollama_url": "http://localhost:11434", "temperature": 0.2,
+                "timeout_seconds": 60}"#,
+        )
+        .unwrap();
+        assert_eq!(config.max_retries, 0);
+
+        let err = AgentConfig::from_json_str(r#"{"name": "coder", "model": 3}"#).unwrap_err();
+        assert!(format!("{err:#}").contains("model"));
+    }
+
+    #[test]
+    fn test_env_overrides_win_and_reject_bad_numbers() {
+        let mut config = AgentConfig {
+            model: "from-file".to_string(),
+            ..Default::default()
+        };
+        config
+            .apply_overrides(|key| match key {
+                "OLLAMA_MODEL" => Some("from-env".to_string()),
+                "OLLAMA_TIMEOUT_SECONDS" => Some("120".to_string()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(config.model, "from-env");
+        assert_eq!(config.timeout_seconds, 120);
+
+        let err = config
+            .apply_overrides(|key| (key == "OLLAMA_TEMPERATURE").then(|| "hot".to_string()))
+            .unwrap_err();
+        assert!(err.to_string().contains("OLLAMA_TEMPERATURE"));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_toml_agents_array() {
+        let file: AgentsFile = parse_toml(
+            r#"
+            [[agents]]
+            name = "a"
+            model = "m"
+            ollama_url = "http://localhost:11434"
+            temperature = 0.7
+            timeout_seconds = 30
+
+            [[agents]]
+            name = "b"
+            model = "m"
+            ollama_url = "http://localhost:11435"
+            temperature = 0.1
+            timeout_seconds = 30
+            "#,
+        )
+        .unwrap();
+        assert_eq!(file.agents.len(), 2);
+    }
+}
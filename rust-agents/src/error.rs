@@ -0,0 +1,47 @@
+// Typed errors returned by the public API
+
+/// Why an operation failed, so callers can tell a timeout from a refused
+/// connection from a malformed reply. Execution paths that produce an
+/// [`crate::AgentResult`] report HTTP rejections and timeouts as its `status`
+/// instead; these errors cover everything that left no result.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum AgentError {
+    #[error("request timed out")]
+    Timeout,
+    #[error("connection failed: {0}")]
+    Connection(String),
+    #[error("HTTP {0}")]
+    HttpStatus(u16),
+    /// The reply was not the JSON shape Ollama documents
+    #[error("failed to decode response: {0}")]
+    Deserialize(String),
+    /// Invalid configuration or arguments, detected before any request
+    #[error("{0}")]
+    Config(String),
+    /// The server answered but reported an error in the body, e.g. an
+    /// `error` line mid-stream
+    #[error("{0}")]
+    Backend(String),
+    /// Reading or writing a local file (configs, checkpoints) failed
+    #[error("{0}")]
+    Io(String),
+    /// The reply outgrew `max_response_bytes` and was abandoned unread
+    #[error("response too large: over {0} bytes")]
+    ResponseTooLarge(usize),
+}
+
+impl AgentError {
+    /// Transport-level failures, which are worth retrying; anything else
+    /// (bad request bodies, decode errors) would fail again.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, AgentError::Timeout | AgentError::Connection(_))
+    }
+}
+
+impl From<serde_json::Error> for AgentError {
+    fn from(err: serde_json::Error) -> Self {
+        AgentError::Deserialize(err.to_string())
+    }
+}
+
+pub type Result<T, E = AgentError> = std::result::Result<T, E>;
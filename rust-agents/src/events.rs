@@ -0,0 +1,118 @@
+// Live execution events for monitoring a batch while it runs
+
+use tokio::{sync::mpsc, task::JoinSet};
+
+use crate::{
+    agent_runtime::{check_lengths, collect_completed, AgentResult, RustAgent},
+    error::{AgentError, Result},
+    telemetry,
+};
+
+/// What happened to one agent of a batch run by
+/// [`execute_parallel_with_events`].
+#[derive(Debug, Clone)]
+pub enum ExecutionEvent {
+    /// The agent at `index` in the input began executing
+    Started { index: usize, agent_id: String },
+    /// The agent produced a result, whatever its status; its `task_index`
+    /// is the agent's index in the input
+    Finished(AgentResult),
+    /// The agent failed without producing a result; its entry in the
+    /// returned batch is a `Failed` result carrying the same error
+    Error {
+        index: usize,
+        agent_id: String,
+        error: AgentError,
+    },
+}
+
+/// Same as [`crate::execute_parallel`], but sends an [`ExecutionEvent`] on
+/// `events` as each agent starts and finishes. Sends wait for channel
+/// capacity, so a slow consumer slows the batch down; size the channel
+/// accordingly. Events are dropped once the receiver is closed.
+pub async fn execute_parallel_with_events(
+    agents: Vec<RustAgent>,
+    tasks: Vec<String>,
+    events: mpsc::Sender<ExecutionEvent>,
+) -> Result<Vec<AgentResult>> {
+    check_lengths(agents.len(), tasks.len())?;
+    let mut set = JoinSet::new();
+
+    for (index, (agent, task)) in agents.into_iter().zip(tasks).enumerate() {
+        let events = events.clone();
+        set.spawn(telemetry::in_current_span(async move {
+            let agent_id = agent.config().name.clone();
+            let _ = events
+                .send(ExecutionEvent::Started {
+                    index,
+                    agent_id: agent_id.clone(),
+                })
+                .await;
+
+            let start = std::time::Instant::now();
+            let outcome = agent
+                .execute(&task)
+                .await
+                .map(|result| result.for_task(index));
+            let event = match &outcome {
+                Ok(result) => ExecutionEvent::Finished(result.clone()),
+                Err(error) => ExecutionEvent::Error {
+                    index,
+                    agent_id,
+                    error: error.clone(),
+                },
+            };
+            let _ = events.send(event).await;
+            agent.settle(outcome, start).for_task(index)
+        }));
+    }
+
+    Ok(collect_completed(set).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        agent_runtime::AgentStatus,
+        mock::{MockBackend, MockReply},
+        AgentConfig,
+    };
+
+    #[tokio::test]
+    async fn test_events_report_start_finish_and_error() {
+        let backend = Arc::new(MockBackend::with_response("ok"));
+        backend.push(MockReply::ConnectionError("refused".to_string()));
+        let agents = (0..2)
+            .map(|_| RustAgent::with_backend(AgentConfig::default(), backend.clone()))
+            .collect();
+        let (tx, mut rx) = mpsc::channel(8);
+
+        let results =
+            execute_parallel_with_events(agents, vec!["a".to_string(), "b".to_string()], tx)
+                .await
+                .unwrap();
+        assert_eq!(results.len(), 2);
+
+        let mut started = 0;
+        let mut finished = 0;
+        let mut errors = 0;
+        while let Some(event) = rx.recv().await {
+            match event {
+                ExecutionEvent::Started { .. } => started += 1,
+                ExecutionEvent::Finished(result) => {
+                    assert_eq!(result.status, AgentStatus::Completed);
+                    assert!(result.task_index.is_some());
+                    finished += 1;
+                },
+                ExecutionEvent::Error { error, .. } => {
+                    assert!(matches!(error, AgentError::Connection(_)));
+                    errors += 1;
+                },
+            }
+        }
+        assert_eq!((started, finished, errors), (2, 1, 1));
+    }
+}
@@ -1,9 +1,70 @@
 // Main library entry point - exposes both Rust API and Python bindings
 
+pub mod adaptive;
 pub mod agent_runtime;
+pub mod backend;
+pub mod batch;
+#[cfg(feature = "cache")]
+mod cache;
+pub mod checkpoint;
+mod circuit;
+pub mod config_file;
+pub mod error;
+pub mod events;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod middleware;
+pub mod mock;
+pub mod models;
+#[cfg(feature = "otel")]
+mod otel;
+pub mod pool;
+mod rate_limit;
+pub mod results_file;
+mod retry;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod session;
+pub mod stats;
+mod streaming;
+mod telemetry;
+pub mod template;
+pub mod tokens;
 
 // Re-export main Rust types for Rust consumers
-pub use agent_runtime::{execute_parallel, AgentConfig, AgentResult, RustAgent};
+pub use adaptive::execute_parallel_adaptive;
+pub use agent_runtime::{
+    execute_parallel, execute_parallel_deadline, execute_parallel_deduplicated,
+    execute_parallel_fail_fast, execute_parallel_graceful, execute_parallel_limited,
+    execute_parallel_ordered, execute_parallel_shared, execute_parallel_stream,
+    execute_parallel_with_cancel, execute_parallel_with_progress,
+    execute_parallel_with_retry_budget, wait_until_ready, AgentConfig, AgentConfigBuilder,
+    AgentResult, AgentStatus, ApiFlavor, ChatMessage, ChunkStrategy, GenerationOptions,
+    OutputFormat, Priority, RustAgent,
+};
+pub use backend::{
+    execute_parallel_balanced, Backend, BackendStats, HttpBackend, LlmBackend, LlmRequest,
+    LlmResponse,
+};
+pub use batch::{spawn_batch, spawn_parallel, AgentBatch, BatchHandle};
+pub use checkpoint::execute_parallel_checkpointed;
+pub use config_file::load_agents;
+pub use error::AgentError;
+pub use events::{execute_parallel_with_events, ExecutionEvent};
+pub use middleware::Middleware;
+pub use mock::{MockBackend, MockReply};
+pub use models::{list_models, ModelInfo, PullProgress};
+pub use pool::AgentPool;
+pub use results_file::{read_results, write_results};
+pub use retry::RetryPolicy;
+pub use session::Session;
+pub use stats::{
+    partition_results, sort_by_duration, LatencyStats, ResultPartition, ThroughputReport,
+};
+pub use streaming::{StreamEvent, StreamProgress};
+pub use template::PromptTemplate;
+pub use tokens::estimate_tokens;
+pub use tokio_util::sync::CancellationToken;
 
 // Python bindings are compiled separately when building as a Python extension
 #[cfg(feature = "python")]
@@ -0,0 +1,133 @@
+// Prometheus metrics for agent executions, behind the `metrics` feature
+
+use std::sync::LazyLock;
+
+use prometheus::{
+    histogram_opts, opts, Encoder, HistogramVec, IntCounterVec, Registry, TextEncoder,
+};
+
+use crate::{agent_runtime::AgentResult, error::AgentError};
+
+struct Metrics {
+    registry: Registry,
+    executions: IntCounterVec,
+    failures: IntCounterVec,
+    duration: HistogramVec,
+    tokens: IntCounterVec,
+}
+
+// A private registry, so embedding applications' own metrics can't collide
+static METRICS: LazyLock<Metrics> = LazyLock::new(|| {
+    let executions = IntCounterVec::new(
+        opts!("agent_executions_total", "Agent requests sent to a backend"),
+        &["model", "status"],
+    )
+    .expect("valid metric");
+    let failures = IntCounterVec::new(
+        opts!(
+            "agent_failures_total",
+            "Agent requests that did not complete"
+        ),
+        &["model", "status"],
+    )
+    .expect("valid metric");
+    let duration = HistogramVec::new(
+        histogram_opts!(
+            "agent_duration_seconds",
+            "Wall-clock time of agent requests, retries included",
+            vec![0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0]
+        ),
+        &["model", "status"],
+    )
+    .expect("valid metric");
+    let tokens = IntCounterVec::new(
+        opts!("agent_tokens_total", "Tokens reported by the backend"),
+        &["model", "kind"],
+    )
+    .expect("valid metric");
+
+    let registry = Registry::new();
+    for collector in [
+        Box::new(executions.clone()) as Box<dyn prometheus::core::Collector>,
+        Box::new(failures.clone()),
+        Box::new(duration.clone()),
+        Box::new(tokens.clone()),
+    ] {
+        registry
+            .register(collector)
+            .expect("metric names are unique");
+    }
+
+    Metrics {
+        registry,
+        executions,
+        failures,
+        duration,
+        tokens,
+    }
+});
+
+/// Every agent metric in the Prometheus text exposition format, ready to be
+/// served from a `/metrics` endpoint.
+pub fn gather() -> String {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&METRICS.registry.gather(), &mut buffer)
+        .expect("text encoding writes to a Vec");
+    String::from_utf8(buffer).expect("the text format is UTF-8")
+}
+
+// Counts one request; an `Err` outcome is labelled with status `error`
+pub(crate) fn record(model: &str, outcome: Result<&AgentResult, &AgentError>) {
+    let metrics = &*METRICS;
+    let status = match outcome {
+        Ok(result) => result.status.as_str(),
+        Err(_) => "error",
+    };
+    let labels = [model, status];
+
+    metrics.executions.with_label_values(&labels).inc();
+    if status != "completed" {
+        metrics.failures.with_label_values(&labels).inc();
+    }
+    if let Ok(result) = outcome {
+        metrics
+            .duration
+            .with_label_values(&labels)
+            .observe(result.duration_ms as f64 / 1000.0);
+        for (kind, count) in [
+            ("prompt", result.prompt_tokens),
+            ("completion", result.completion_tokens),
+        ] {
+            if let Some(count) = count {
+                metrics
+                    .tokens
+                    .with_label_values(&[model, kind])
+                    .inc_by(count);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{mock::MockBackend, AgentConfig, RustAgent};
+
+    #[tokio::test]
+    async fn test_gather_reports_executions_by_model_and_status() {
+        let config = AgentConfig {
+            model: "metrics-test-model".to_string(),
+            ..AgentConfig::default()
+        };
+        let agent = RustAgent::with_backend(config, Arc::new(MockBackend::with_response("ok")));
+        agent.execute("hi").await.unwrap();
+
+        let text = super::gather();
+        assert!(text.contains(
+            r#"agent_executions_total{model="metrics-test-model",status="completed"} 1"#
+        ));
+        assert!(text.contains("agent_duration_seconds_bucket"));
+    }
+}
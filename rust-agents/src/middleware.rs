@@ -0,0 +1,24 @@
+// Extension point for rewriting requests and results around each execution
+
+use async_trait::async_trait;
+
+use crate::{agent_runtime::AgentResult, backend::LlmRequest};
+
+/// Hooks run by a [`crate::RustAgent`] around every request, in the order
+/// they were added with [`crate::RustAgent::with_middleware`]. Both methods
+/// default to doing nothing, so implement only what you need. Agents with
+/// middleware bypass the response cache and are never merged by
+/// [`crate::execute_parallel_deduplicated`].
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// Called before a request is first sent; retries resend the rewritten
+    /// request. May change the body (e.g. to inject guardrails into the
+    /// prompt) or headers.
+    async fn before(&self, _request: &mut LlmRequest) {}
+
+    /// Called on every result an execution returns, whatever its status:
+    /// completed, failed, and timed-out results and cache hits alike. Not
+    /// called when the execution ends in an error or is cancelled. May
+    /// rewrite the output (e.g. to redact secrets).
+    async fn after(&self, _result: &mut AgentResult) {}
+}
@@ -0,0 +1,114 @@
+// In-process backend for exercising agents without a live Ollama
+
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+use async_trait::async_trait;
+
+use crate::{
+    backend::{LlmBackend, LlmRequest, LlmResponse},
+    error::{AgentError, Result},
+};
+
+/// What a [`MockBackend`] does when it receives a request.
+#[derive(Debug, Clone)]
+pub enum MockReply {
+    Response(LlmResponse),
+    /// Fail as if the request exceeded its timeout
+    Timeout,
+    /// Fail as if the server could not be reached
+    ConnectionError(String),
+}
+
+/// [`LlmBackend`] that answers from a queue of canned replies, falling back to
+/// a fixed default once the queue is empty. Every request is recorded so tests
+/// can assert on what was sent.
+pub struct MockBackend {
+    queue: Mutex<VecDeque<MockReply>>,
+    default: MockReply,
+    delay: Option<Duration>,
+    requests: Mutex<Vec<LlmRequest>>,
+}
+
+impl MockBackend {
+    /// A backend that always answers with `text` as the generated output, in
+    /// both the `/api/generate` and `/api/chat` response shapes.
+    pub fn with_response(text: &str) -> Self {
+        Self::with_reply(MockReply::Response(LlmResponse::json_ok(
+            &serde_json::json!({
+                "response": text,
+                "message": { "role": "assistant", "content": text },
+                "done": true,
+            }),
+        )))
+    }
+
+    pub fn with_reply(default: MockReply) -> Self {
+        Self {
+            queue: Mutex::new(VecDeque::new()),
+            default,
+            delay: None,
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Waits `delay` before every reply, e.g. to exercise cancellation.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Queues a one-off reply, served before the default.
+    pub fn push(&self, reply: MockReply) -> &Self {
+        self.lock_queue().push_back(reply);
+        self
+    }
+
+    /// Queues a one-off response with the given status and JSON body.
+    pub fn push_json(&self, status: u16, body: serde_json::Value) -> &Self {
+        let mut response = LlmResponse::json_ok(&body);
+        response.status = status;
+        self.push(MockReply::Response(response))
+    }
+
+    /// Requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<LlmRequest> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn lock_queue(&self) -> std::sync::MutexGuard<'_, VecDeque<MockReply>> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl LlmBackend for MockBackend {
+    async fn generate(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let limit = request.max_response_bytes;
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(request);
+
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+
+        let reply = self
+            .lock_queue()
+            .pop_front()
+            .unwrap_or_else(|| self.default.clone());
+        match reply {
+            MockReply::Response(response) => match limit {
+                Some(limit) if response.body.len() > limit => {
+                    Err(AgentError::ResponseTooLarge(limit))
+                },
+                _ => Ok(response),
+            },
+            MockReply::Timeout => Err(AgentError::Timeout),
+            MockReply::ConnectionError(message) => Err(AgentError::Connection(message)),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+// Discovering which models an Ollama server has installed
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agent_runtime::{build_client, AgentConfig},
+    backend::{send_error, transport_error},
+    error::{AgentError, Result},
+};
+
+/// One installed model, as listed by Ollama's `/api/tags`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub name: String,
+    /// Size on disk in bytes
+    pub size: u64,
+    /// When the model was last pulled or modified, as an RFC 3339 timestamp
+    pub modified_at: String,
+}
+
+/// One status line streamed by Ollama's `/api/pull`, e.g. `pulling manifest`
+/// or `success`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PullProgress {
+    pub status: String,
+    /// Bytes of the current layer downloaded so far, while downloading
+    #[serde(default)]
+    pub completed: Option<u64>,
+    /// Size of the current layer in bytes, while downloading
+    #[serde(default)]
+    pub total: Option<u64>,
+}
+
+// One line of the pull stream: `None` for blank lines, an `error` line is a
+// backend error
+pub(crate) fn parse_pull_line(line: &[u8]) -> Result<Option<PullProgress>> {
+    let line = line.trim_ascii();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let value: serde_json::Value = serde_json::from_slice(line)?;
+    if let Some(error) = value.get("error").and_then(|e| e.as_str()) {
+        return Err(AgentError::Backend(error.to_string()));
+    }
+    Ok(Some(serde_json::from_value(value)?))
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    models: Vec<ModelInfo>,
+}
+
+/// The models installed on the Ollama server at `ollama_url`, over a client
+/// set up as for a [`crate::RustAgent`] with the default config. Gives up
+/// with [`AgentError::Timeout`] after the default `timeout_seconds`.
+pub async fn list_models(ollama_url: &str) -> Result<Vec<ModelInfo>> {
+    let client = build_client(&AgentConfig::default())?;
+    list_models_with_client(&client, ollama_url).await
+}
+
+/// Same as [`list_models`], but reuses an existing client. The request is
+/// bounded by the default `timeout_seconds` whatever the client's settings.
+pub async fn list_models_with_client(
+    client: &reqwest::Client,
+    ollama_url: &str,
+) -> Result<Vec<ModelInfo>> {
+    let timeout = Duration::from_secs(AgentConfig::default().timeout_seconds);
+    fetch_tags(client, ollama_url, timeout).await
+}
+
+async fn fetch_tags(
+    client: &reqwest::Client,
+    ollama_url: &str,
+    timeout: Duration,
+) -> Result<Vec<ModelInfo>> {
+    let response = client
+        .get(format!("{ollama_url}/api/tags"))
+        .timeout(timeout)
+        .send()
+        .await
+        .map_err(|err| send_error(err, ollama_url))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(AgentError::HttpStatus(status.as_u16()));
+    }
+
+    let body = response.bytes().await.map_err(transport_error)?;
+    parse_tags(&body)
+}
+
+fn parse_tags(body: &[u8]) -> Result<Vec<ModelInfo>> {
+    let tags: TagsResponse = serde_json::from_slice(body)?;
+    Ok(tags.models)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tags_keeps_name_size_and_timestamp() {
+        let models = parse_tags(
+            br#"{"models": [{
+                "name": "qwen2.5-coder:14b",
+                "model": "qwen2.5-coder:14b",
+                "modified_at": "2025-05-04T17:37:44.706015396-07:00",
+                "size": 8988124069,
+                "digest": "9f438cb9cd581fc025612d27f7c1a6669ff83a8bb0ed86c94fcf4c5440555697",
+                "details": {"family": "qwen2", "parameter_size": "14.8B"}
+            }]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            models,
+            [ModelInfo {
+                name: "qwen2.5-coder:14b".to_string(),
+                size: 8988124069,
+                modified_at: "2025-05-04T17:37:44.706015396-07:00".to_string(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_silent_server_times_out() {
+        // Accepts connections but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let _open = listener.accept().await;
+            std::future::pending::<()>().await;
+        });
+
+        let err = fetch_tags(&reqwest::Client::new(), &url, Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AgentError::Timeout), "{err}");
+    }
+}
@@ -0,0 +1,135 @@
+// OpenTelemetry spans and W3C trace context propagation, behind the `otel`
+// feature. Spans go to whatever tracer provider the application installed
+// globally; without one they are no-ops but the trace id still propagates.
+
+use opentelemetry::{
+    global::{self, BoxedSpan},
+    trace::{
+        SpanContext, SpanId, SpanKind, Status, TraceContextExt, TraceFlags, TraceId, TraceState,
+        Tracer,
+    },
+    Context, KeyValue,
+};
+
+use crate::{
+    agent_runtime::{AgentConfig, AgentResult, AgentStatus},
+    error::AgentError,
+};
+
+/// The header outgoing requests carry the execution span's context in.
+pub(crate) const TRACEPARENT: &str = "traceparent";
+
+/// Parses a W3C `traceparent` header, `00-<trace id>-<parent id>-<flags>`,
+/// into a context whose remote span is the caller's.
+pub(crate) fn parse_traceparent(header: &str) -> Result<Context, String> {
+    let invalid = || format!("invalid traceparent '{header}'");
+    let parts: Vec<&str> = header.trim().split('-').collect();
+    let [version, trace_id, span_id, flags] = parts[..] else {
+        return Err(invalid());
+    };
+    if version != "00" || trace_id.len() != 32 || span_id.len() != 16 || flags.len() != 2 {
+        return Err(invalid());
+    }
+
+    let trace_id = TraceId::from_hex(trace_id).map_err(|_| invalid())?;
+    let span_id = SpanId::from_hex(span_id).map_err(|_| invalid())?;
+    let flags = u8::from_str_radix(flags, 16).map_err(|_| invalid())?;
+    let span_context = SpanContext::new(
+        trace_id,
+        span_id,
+        TraceFlags::new(flags),
+        true,
+        TraceState::default(),
+    );
+    if !span_context.is_valid() {
+        return Err(invalid());
+    }
+    Ok(Context::new().with_remote_span_context(span_context))
+}
+
+/// Formats `span_context` as a `traceparent` header value.
+pub(crate) fn traceparent(span_context: &SpanContext) -> String {
+    format!(
+        "00-{}-{}-{:02x}",
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags().to_u8()
+    )
+}
+
+/// Starts a client span for one execution of `config` as a child of `parent`.
+pub(crate) fn start_span(parent: &Context, config: &AgentConfig) -> BoxedSpan {
+    let tracer = global::tracer("agent-runtime");
+    tracer
+        .span_builder("agent.execute")
+        .with_kind(SpanKind::Client)
+        .with_attributes([
+            KeyValue::new("agent.id", config.name.clone()),
+            KeyValue::new("agent.model", config.model.clone()),
+            KeyValue::new("server.address", config.ollama_url.clone()),
+        ])
+        .start_with_context(&tracer, parent)
+}
+
+/// Records the outcome on `span` and ends it.
+pub(crate) fn end_span(span: &mut BoxedSpan, outcome: Result<&AgentResult, &AgentError>) {
+    use opentelemetry::trace::Span;
+
+    match outcome {
+        Ok(result) => {
+            span.set_attribute(KeyValue::new("agent.status", result.status.as_str()));
+            span.set_attribute(KeyValue::new(
+                "agent.duration_ms",
+                result.duration_ms as i64,
+            ));
+            if result.status == AgentStatus::Completed {
+                span.set_status(Status::Ok);
+            } else {
+                span.set_status(Status::error(result.error.clone().unwrap_or_default()));
+            }
+        },
+        Err(e) => span.set_status(Status::error(e.to_string())),
+    }
+    span.end();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{mock::MockBackend, RustAgent};
+
+    const PARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_parse_traceparent_round_trips_and_rejects_garbage() {
+        let parent = parse_traceparent(PARENT).unwrap();
+        assert_eq!(traceparent(parent.span().span_context()), PARENT);
+
+        for bad in [
+            "",
+            "00-xyz-00f067aa0ba902b7-01",
+            PARENT.replace("00-", "01-").as_str(),
+        ] {
+            assert!(parse_traceparent(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_traced_sends_the_trace_id() {
+        let backend = Arc::new(MockBackend::with_response("ok"));
+        let agent = RustAgent::with_backend(AgentConfig::default(), backend.clone());
+
+        agent.execute_traced("hi", PARENT).await.unwrap();
+        let request = &backend.requests()[0];
+        let (_, sent) = request
+            .headers
+            .iter()
+            .find(|(name, _)| name == TRACEPARENT)
+            .unwrap();
+        assert!(sent.contains("4bf92f3577b34da6a3ce929d0e0e4736"));
+
+        assert!(agent.execute_traced("hi", "nonsense").await.is_err());
+    }
+}
@@ -0,0 +1,134 @@
+// Shared HTTP connection pool for constructing many agents
+
+use std::sync::Arc;
+
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{
+    agent_runtime::{build_client, AgentConfig, AgentResult, RustAgent},
+    error::{AgentError, Result},
+};
+
+/// Hands out agents that all share one `reqwest::Client`, and therefore one
+/// connection pool. Cloning the pool is cheap and shares the same client.
+#[derive(Clone)]
+pub struct AgentPool {
+    client: reqwest::Client,
+}
+
+impl AgentPool {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .build()
+                .map_err(|e| AgentError::Config(format!("failed to build HTTP client: {e}")))?,
+        })
+    }
+
+    /// A pool whose client takes its connection-level settings (timeouts,
+    /// pool tuning, proxy, TLS) from `config`, as [`RustAgent::new`] would.
+    pub fn from_config(config: &AgentConfig) -> Result<Self> {
+        Ok(Self {
+            client: build_client(config)?,
+        })
+    }
+
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// Builds an agent for `config` backed by the pool's client. As with
+    /// [`RustAgent::with_client`], the client's connection-level settings
+    /// (timeouts, pool tuning, proxy, TLS, user agent) apply rather than
+    /// `config`'s; use [`AgentPool::from_config`] to choose them.
+    pub fn agent(&self, config: AgentConfig) -> RustAgent {
+        RustAgent::with_client(config, self.client.clone())
+    }
+
+    /// Starts `workers` tasks that take `(config, task)` pairs from `tasks`
+    /// as they arrive, execute them on the pool's client, and send each
+    /// result to `results` in completion order. With bounded channels this
+    /// applies backpressure both ways: submitters wait while every worker is
+    /// busy and the queue is full, and workers wait while `results` is full.
+    /// The returned handle finishes once `tasks` is closed and drained, or
+    /// once `results` is dropped.
+    pub fn spawn_workers(
+        &self,
+        workers: usize,
+        tasks: mpsc::Receiver<(AgentConfig, String)>,
+        results: mpsc::Sender<AgentResult>,
+    ) -> Result<JoinHandle<()>> {
+        if workers == 0 {
+            return Err(AgentError::Config(
+                "spawn_workers needs at least 1 worker".to_string(),
+            ));
+        }
+
+        let tasks = Arc::new(tokio::sync::Mutex::new(tasks));
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                let pool = self.clone();
+                let tasks = Arc::clone(&tasks);
+                let results = results.clone();
+                tokio::spawn(async move {
+                    loop {
+                        // Held only while waiting for the next task, not while
+                        // running it
+                        let next = tasks.lock().await.recv().await;
+                        let Some((config, task)) = next else {
+                            return;
+                        };
+                        let result = pool.agent(config).execute_settled(&task).await;
+                        if results.send(result).await.is_err() {
+                            return;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        Ok(tokio::spawn(async move {
+            for handle in handles {
+                let _ = handle.await;
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_workers_drain_the_queue_until_it_closes() {
+        let pool = AgentPool::new().unwrap();
+        let (task_tx, task_rx) = mpsc::channel(2);
+        let (result_tx, mut result_rx) = mpsc::channel(2);
+        let workers = pool.spawn_workers(2, task_rx, result_tx).unwrap();
+
+        tokio::spawn(async move {
+            for i in 0..5 {
+                let config = AgentConfig {
+                    // Nothing listens here, so every task fails fast
+                    ollama_url: "http://127.0.0.1:9".to_string(),
+                    ..AgentConfig::default()
+                };
+                task_tx.send((config, format!("task {i}"))).await.unwrap();
+            }
+        });
+
+        let mut received = 0;
+        while result_rx.recv().await.is_some() {
+            received += 1;
+        }
+        assert_eq!(received, 5);
+        workers.await.unwrap();
+        assert!(pool
+            .spawn_workers(0, mpsc::channel(1).1, mpsc::channel(1).0)
+            .is_err());
+    }
+}
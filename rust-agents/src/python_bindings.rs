@@ -1,8 +1,42 @@
-use agent_runtime::{execute_parallel, AgentConfig, RustAgent};
-use pyo3::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use agent_runtime::{
+    execute_parallel_ordered, execute_parallel_stream, execute_parallel_with_cancel,
+    execute_parallel_with_progress, AgentConfig, AgentStatus, ApiFlavor, ChunkStrategy,
+    GenerationOptions, OutputFormat, Priority, RustAgent,
+};
+use futures::StreamExt;
+use pyo3::{
+    conversion::FromPyObjectOwned,
+    create_exception,
+    exceptions::{
+        PyConnectionError, PyKeyboardInterrupt, PyOSError, PyRuntimeError, PyStopAsyncIteration,
+        PyTimeoutError, PyValueError,
+    },
+    prelude::*,
+    types::{PyCFunction, PyDict},
+};
 use pyo3_async_runtimes;
+use tokio::sync::mpsc;
+
+use crate::{
+    agent_runtime, circuit, rate_limit, AgentError, AgentPool, CancellationToken, LatencyStats,
+    ModelInfo, PromptTemplate, RetryPolicy, Session, StreamEvent, ThroughputReport,
+};
 
-use crate::agent_runtime;
+// One exception type per `AgentError` variant. Each subclasses the closest
+// builtin, so existing `except ValueError` / `except RuntimeError` handlers
+// keep working.
+create_exception!(agent_runtime_py, AgentTimeoutError, PyTimeoutError);
+create_exception!(agent_runtime_py, AgentConnectionError, PyConnectionError);
+create_exception!(agent_runtime_py, HttpStatusError, PyRuntimeError);
+create_exception!(agent_runtime_py, DeserializeError, PyValueError);
+create_exception!(agent_runtime_py, ConfigError, PyValueError);
+create_exception!(agent_runtime_py, BackendError, PyRuntimeError);
+create_exception!(agent_runtime_py, AgentIoError, PyOSError);
 
 /// Python-facing agent result
 #[pyclass]
@@ -11,13 +45,107 @@ pub struct PyAgentResult {
     #[pyo3(get)]
     pub agent_id: String,
     #[pyo3(get)]
-    pub status: String,
+    pub run_id: String,
+    pub status: AgentStatus,
     #[pyo3(get)]
     pub output: Option<String>,
     #[pyo3(get)]
     pub error: Option<String>,
     #[pyo3(get)]
     pub execution_time: f64,
+    #[pyo3(get)]
+    pub prompt_tokens: Option<u64>,
+    #[pyo3(get)]
+    pub completion_tokens: Option<u64>,
+    #[pyo3(get)]
+    pub eval_duration_ms: Option<u64>,
+    #[pyo3(get)]
+    pub used_fallback: bool,
+    #[pyo3(get)]
+    pub metadata: HashMap<String, String>,
+    /// Milliseconds until the first streamed chunk; only set by
+    /// `execute_agent_streamed`
+    #[pyo3(get)]
+    pub first_token_ms: Option<u64>,
+    /// Index of the input this result answers; for `execute_agents_batch`
+    /// the position in `inputs`, otherwise in the agents passed in
+    #[pyo3(get)]
+    pub task_index: Option<usize>,
+}
+
+#[pymethods]
+impl PyAgentResult {
+    /// Status as its serialized string, e.g. `"completed"` or `"timed_out"`
+    #[getter]
+    fn status(&self) -> &'static str {
+        self.status.as_str()
+    }
+
+    /// Every field as a plain dict, ready for `json.dumps` or a DataFrame
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("agent_id", &self.agent_id)?;
+        dict.set_item("run_id", &self.run_id)?;
+        dict.set_item("status", self.status.as_str())?;
+        dict.set_item("output", &self.output)?;
+        dict.set_item("error", &self.error)?;
+        dict.set_item("execution_time", self.execution_time)?;
+        dict.set_item("prompt_tokens", self.prompt_tokens)?;
+        dict.set_item("completion_tokens", self.completion_tokens)?;
+        dict.set_item("eval_duration_ms", self.eval_duration_ms)?;
+        dict.set_item("used_fallback", self.used_fallback)?;
+        dict.set_item("metadata", &self.metadata)?;
+        dict.set_item("first_token_ms", self.first_token_ms)?;
+        dict.set_item("task_index", self.task_index)?;
+        Ok(dict)
+    }
+
+    /// The inverse of `to_dict`. Only `agent_id` and `status` are required;
+    /// a missing `run_id` gets a fresh one. Raises `ConfigError` for an
+    /// unknown status or malformed `run_id`.
+    #[staticmethod]
+    fn from_dict(data: &Bound<'_, PyDict>) -> PyResult<Self> {
+        let required = |key: &str| {
+            dict_item::<String>(data, key)?
+                .ok_or_else(|| agent_err(AgentError::Config(format!("missing '{key}'"))))
+        };
+        let status = required("status")?;
+        let status = serde_json::from_value(serde_json::json!(status))
+            .map_err(|_| agent_err(AgentError::Config(format!("unknown status '{status}'"))))?;
+        let run_id = match dict_item::<String>(data, "run_id")? {
+            Some(run_id) => run_id
+                .parse::<uuid::Uuid>()
+                .map_err(|_| agent_err(AgentError::Config(format!("invalid run_id '{run_id}'"))))?,
+            None => uuid::Uuid::new_v4(),
+        };
+
+        Ok(PyAgentResult {
+            agent_id: required("agent_id")?,
+            run_id: run_id.to_string(),
+            status,
+            output: dict_item(data, "output")?,
+            error: dict_item(data, "error")?,
+            execution_time: dict_item(data, "execution_time")?.unwrap_or_default(),
+            prompt_tokens: dict_item(data, "prompt_tokens")?,
+            completion_tokens: dict_item(data, "completion_tokens")?,
+            eval_duration_ms: dict_item(data, "eval_duration_ms")?,
+            used_fallback: dict_item(data, "used_fallback")?.unwrap_or_default(),
+            metadata: dict_item(data, "metadata")?.unwrap_or_default(),
+            first_token_ms: dict_item(data, "first_token_ms")?,
+            task_index: dict_item(data, "task_index")?,
+        })
+    }
+}
+
+// `data[key]` converted to `T`, with a missing key and `None` both as `None`
+fn dict_item<'py, T: FromPyObjectOwned<'py>>(
+    data: &Bound<'py, PyDict>,
+    key: &str,
+) -> PyResult<Option<T>> {
+    match data.get_item(key)? {
+        Some(value) if !value.is_none() => value.extract().map(Some).map_err(Into::into),
+        _ => Ok(None),
+    }
 }
 
 /// Python-facing agent configuration
@@ -34,6 +162,104 @@ pub struct PyAgentConfig {
     pub temperature: f32,
     #[pyo3(get, set)]
     pub timeout_seconds: u64,
+    #[pyo3(get, set)]
+    pub max_retries: u32,
+    #[pyo3(get, set)]
+    pub retry_policy: String,
+    #[pyo3(get, set)]
+    pub retry_backoff_ms: u64,
+    #[pyo3(get, set)]
+    pub retry_jitter: bool,
+    #[pyo3(get, set)]
+    pub top_p: Option<f32>,
+    #[pyo3(get, set)]
+    pub top_k: Option<u32>,
+    #[pyo3(get, set)]
+    pub num_predict: Option<i32>,
+    #[pyo3(get, set)]
+    pub seed: Option<i64>,
+    #[pyo3(get, set)]
+    pub repeat_penalty: Option<f32>,
+    #[pyo3(get, set)]
+    pub num_ctx: Option<u32>,
+    #[pyo3(get, set)]
+    pub stop: Vec<String>,
+    /// Send the input verbatim, skipping the Modelfile's prompt template; the
+    /// input must then already be in the model's prompt format
+    #[pyo3(get, set)]
+    pub raw: bool,
+    #[pyo3(get, set)]
+    pub system: Option<String>,
+    #[pyo3(get, set)]
+    pub keep_alive: Option<String>,
+    /// `"text"` or `"json"`
+    #[pyo3(get, set)]
+    pub format: String,
+    #[pyo3(get, set)]
+    pub validate_json: bool,
+    #[pyo3(get, set)]
+    pub strip_markdown_fences: bool,
+    #[pyo3(get, set)]
+    pub trim_output: bool,
+    #[pyo3(get, set)]
+    pub auto_pull: bool,
+    #[pyo3(get, set)]
+    pub failure_threshold: u32,
+    #[pyo3(get, set)]
+    pub cooldown_ms: u64,
+    #[pyo3(get, set)]
+    pub headers: HashMap<String, String>,
+    #[pyo3(get, set)]
+    pub bearer_token: Option<String>,
+    #[pyo3(get, set)]
+    pub fallback_model: Option<String>,
+    #[pyo3(get, set)]
+    pub requests_per_second: Option<f64>,
+    /// `"ollama_native"` or `"openai_compatible"`
+    #[pyo3(get, set)]
+    pub api_flavor: String,
+    #[pyo3(get, set)]
+    pub cache_capacity: usize,
+    #[pyo3(get, set)]
+    pub cache_ttl_seconds: Option<u64>,
+    #[pyo3(get, set)]
+    pub priority: String,
+    #[pyo3(get, set)]
+    pub max_prompt_chars: Option<usize>,
+    #[pyo3(get, set)]
+    pub chunk_strategy: String,
+    #[pyo3(get, set)]
+    pub max_response_bytes: Option<usize>,
+    #[pyo3(get, set)]
+    pub connect_timeout_seconds: Option<u64>,
+    #[pyo3(get, set)]
+    pub read_timeout_seconds: Option<u64>,
+    #[pyo3(get, set)]
+    pub pool_idle_timeout_seconds: Option<u64>,
+    #[pyo3(get, set)]
+    pub pool_max_idle_per_host: Option<usize>,
+    #[pyo3(get, set)]
+    pub response_path: Option<String>,
+    /// JSON text of a request body template with `{prompt}`, `{model}`,
+    /// and `{temperature}` placeholders, sent instead of the usual body
+    #[pyo3(get, set)]
+    pub request_template: Option<String>,
+    /// Copied into each result's `metadata`; never sent to the server
+    #[pyo3(get, set)]
+    pub metadata: HashMap<String, String>,
+    #[pyo3(get, set)]
+    pub proxy_url: Option<String>,
+    #[pyo3(get, set)]
+    pub no_proxy: Option<String>,
+    /// PEM CA certificate(s) to trust in addition to the system roots
+    #[pyo3(get, set)]
+    pub tls_root_cert_pem: Option<String>,
+    /// DANGEROUS: disables certificate verification; testing only
+    #[pyo3(get, set)]
+    pub tls_accept_invalid_certs: bool,
+    /// `User-Agent` header; `None` sends `self-hosted-ai-agents/<version>`
+    #[pyo3(get, set)]
+    pub user_agent: Option<String>,
 }
 
 #[pymethods]
@@ -46,25 +272,192 @@ impl PyAgentConfig {
         temperature: f32,
         timeout_seconds: u64,
     ) -> Self {
+        let defaults = AgentConfig::default();
         PyAgentConfig {
             name,
             model,
             ollama_url,
             temperature,
             timeout_seconds,
+            max_retries: defaults.max_retries,
+            retry_policy: "on_server_error".to_string(),
+            retry_backoff_ms: defaults.retry_backoff_ms,
+            retry_jitter: defaults.retry_jitter,
+            top_p: None,
+            top_k: None,
+            num_predict: None,
+            seed: None,
+            repeat_penalty: None,
+            num_ctx: None,
+            stop: Vec::new(),
+            raw: false,
+            system: None,
+            keep_alive: None,
+            format: "text".to_string(),
+            validate_json: false,
+            strip_markdown_fences: false,
+            trim_output: false,
+            auto_pull: false,
+            failure_threshold: defaults.failure_threshold,
+            cooldown_ms: defaults.cooldown_ms,
+            headers: HashMap::new(),
+            bearer_token: None,
+            fallback_model: None,
+            requests_per_second: None,
+            api_flavor: "ollama_native".to_string(),
+            cache_capacity: defaults.cache_capacity,
+            cache_ttl_seconds: None,
+            priority: "normal".to_string(),
+            max_prompt_chars: None,
+            max_response_bytes: None,
+            chunk_strategy: "fail".to_string(),
+            connect_timeout_seconds: None,
+            read_timeout_seconds: None,
+            pool_idle_timeout_seconds: None,
+            pool_max_idle_per_host: None,
+            response_path: None,
+            request_template: None,
+            metadata: HashMap::new(),
+            proxy_url: None,
+            no_proxy: None,
+            tls_root_cert_pem: None,
+            tls_accept_invalid_certs: false,
+            user_agent: None,
         }
     }
 }
 
-impl From<PyAgentConfig> for AgentConfig {
-    fn from(py_config: PyAgentConfig) -> Self {
-        AgentConfig {
+impl TryFrom<PyAgentConfig> for AgentConfig {
+    type Error = PyErr;
+
+    fn try_from(py_config: PyAgentConfig) -> PyResult<Self> {
+        let format = match py_config.format.as_str() {
+            "text" => OutputFormat::Text,
+            "json" => OutputFormat::Json,
+            other => {
+                return Err(ConfigError::new_err(format!(
+                    "unknown format '{other}', expected 'text' or 'json'"
+                )))
+            },
+        };
+
+        let request_template = py_config
+            .request_template
+            .as_deref()
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(|e| ConfigError::new_err(format!("invalid request_template JSON: {e}")))?;
+
+        let api_flavor = match py_config.api_flavor.as_str() {
+            "ollama_native" => ApiFlavor::OllamaNative,
+            "openai_compatible" => ApiFlavor::OpenAiCompatible,
+            other => {
+                return Err(ConfigError::new_err(format!(
+                    "unknown api_flavor '{other}', expected 'ollama_native' or 'openai_compatible'"
+                )))
+            },
+        };
+        let retry_policy = match py_config.retry_policy.as_str() {
+            "never" => RetryPolicy::Never,
+            "on_network" => RetryPolicy::OnNetwork,
+            "on_server_error" => RetryPolicy::OnServerError,
+            other => {
+                return Err(ConfigError::new_err(format!(
+                    "unknown retry_policy '{other}', expected 'never', 'on_network', or 'on_server_error'"
+                )))
+            },
+        };
+        let priority = match py_config.priority.as_str() {
+            "high" => Priority::High,
+            "normal" => Priority::Normal,
+            "low" => Priority::Low,
+            other => {
+                return Err(ConfigError::new_err(format!(
+                    "unknown priority '{other}', expected 'high', 'normal', or 'low'"
+                )))
+            },
+        };
+        let chunk_strategy = match py_config.chunk_strategy.as_str() {
+            "fail" => ChunkStrategy::Fail,
+            "split" => ChunkStrategy::Split,
+            other => {
+                return Err(ConfigError::new_err(format!(
+                    "unknown chunk_strategy '{other}', expected 'fail' or 'split'"
+                )))
+            },
+        };
+        if std::time::Duration::from_millis(py_config.cooldown_ms) > circuit::MAX_COOLDOWN {
+            return Err(ConfigError::new_err(format!(
+                "cooldown_ms must be at most {} (one day), got {}",
+                circuit::MAX_COOLDOWN.as_millis(),
+                py_config.cooldown_ms
+            )));
+        }
+        if let Some(rate) = py_config.requests_per_second {
+            if !rate_limit::is_valid_rate(rate) {
+                return Err(ConfigError::new_err(format!(
+                    "requests_per_second must be at least {} (one request a day), got {rate}",
+                    rate_limit::MIN_REQUESTS_PER_SECOND
+                )));
+            }
+        }
+
+        let config = AgentConfig {
             name: py_config.name,
             model: py_config.model,
             ollama_url: py_config.ollama_url,
             temperature: py_config.temperature,
             timeout_seconds: py_config.timeout_seconds,
-        }
+            connect_timeout_seconds: py_config.connect_timeout_seconds,
+            read_timeout_seconds: py_config.read_timeout_seconds,
+            pool_idle_timeout_seconds: py_config.pool_idle_timeout_seconds,
+            pool_max_idle_per_host: py_config.pool_max_idle_per_host,
+            max_retries: py_config.max_retries,
+            retry_policy,
+            retry_backoff_ms: py_config.retry_backoff_ms,
+            retry_jitter: py_config.retry_jitter,
+            options: GenerationOptions {
+                top_p: py_config.top_p,
+                top_k: py_config.top_k,
+                num_predict: py_config.num_predict,
+                seed: py_config.seed,
+                repeat_penalty: py_config.repeat_penalty,
+                num_ctx: py_config.num_ctx,
+                stop: py_config.stop,
+                raw: py_config.raw,
+            },
+            system: py_config.system,
+            keep_alive: py_config.keep_alive,
+            format,
+            validate_json: py_config.validate_json,
+            strip_markdown_fences: py_config.strip_markdown_fences,
+            trim_output: py_config.trim_output,
+            auto_pull: py_config.auto_pull,
+            failure_threshold: py_config.failure_threshold,
+            cooldown_ms: py_config.cooldown_ms,
+            headers: py_config.headers,
+            bearer_token: py_config.bearer_token,
+            fallback_model: py_config.fallback_model,
+            requests_per_second: py_config.requests_per_second,
+            api_flavor,
+            cache_capacity: py_config.cache_capacity,
+            cache_ttl_seconds: py_config.cache_ttl_seconds,
+            priority,
+            max_prompt_chars: py_config.max_prompt_chars,
+            max_response_bytes: py_config.max_response_bytes,
+            chunk_strategy,
+            response_path: py_config.response_path,
+            request_template,
+            metadata: py_config.metadata,
+            proxy_url: py_config.proxy_url,
+            no_proxy: py_config.no_proxy,
+            tls_root_cert_pem: py_config.tls_root_cert_pem,
+            tls_accept_invalid_certs: py_config.tls_accept_invalid_certs,
+            user_agent: py_config.user_agent,
+        };
+        config.validate_headers().map_err(agent_err)?;
+        config.validate_request_template().map_err(agent_err)?;
+        Ok(config)
     }
 }
 
@@ -72,35 +465,141 @@ impl From<agent_runtime::AgentResult> for PyAgentResult {
     fn from(result: agent_runtime::AgentResult) -> Self {
         PyAgentResult {
             agent_id: result.agent_id,
+            run_id: result.run_id.to_string(),
             status: result.status,
             output: result.output,
             error: result.error,
             execution_time: result.duration_ms as f64,
+            prompt_tokens: result.prompt_tokens,
+            completion_tokens: result.completion_tokens,
+            eval_duration_ms: result.eval_duration_ms,
+            used_fallback: result.used_fallback,
+            metadata: result.metadata,
+            first_token_ms: result.first_token_ms,
+            task_index: result.task_index,
+        }
+    }
+}
+
+impl From<PyAgentResult> for agent_runtime::AgentResult {
+    fn from(result: PyAgentResult) -> Self {
+        Self {
+            agent_id: result.agent_id,
+            // `run_id` is read-only on the Python side, so it always parses
+            run_id: result.run_id.parse().unwrap_or_default(),
+            status: result.status,
+            output: result.output,
+            error: result.error,
+            duration_ms: result.execution_time as u128,
+            prompt_tokens: result.prompt_tokens,
+            completion_tokens: result.completion_tokens,
+            eval_duration_ms: result.eval_duration_ms,
+            used_fallback: result.used_fallback,
+            metadata: result.metadata,
+            first_token_ms: result.first_token_ms,
+            task_index: result.task_index,
+            schema_version: Self::SCHEMA_VERSION,
         }
     }
 }
 
+/// Handle for aborting in-flight executions. Pass it to any execute function
+/// and call `cancel()` from another task; agents still running finish with
+/// status `"cancelled"`.
+#[pyclass(name = "CancellationToken")]
+#[derive(Clone, Default)]
+pub struct PyCancellationToken {
+    token: CancellationToken,
+}
+
+#[pymethods]
+impl PyCancellationToken {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    #[getter]
+    fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+}
+
+fn build_agent(id: String, mut config: PyAgentConfig) -> PyResult<RustAgent> {
+    config.name = id;
+    RustAgent::new(config.try_into()?).map_err(agent_err)
+}
+
+fn agent_err(e: AgentError) -> PyErr {
+    let message = e.to_string();
+    match e {
+        AgentError::Timeout => AgentTimeoutError::new_err(message),
+        AgentError::Connection(_) => AgentConnectionError::new_err(message),
+        AgentError::HttpStatus(_) => HttpStatusError::new_err(message),
+        AgentError::Deserialize(_) => DeserializeError::new_err(message),
+        AgentError::Config(_) => ConfigError::new_err(message),
+        AgentError::Backend(_) | AgentError::ResponseTooLarge(_) => BackendError::new_err(message),
+        AgentError::Io(_) => AgentIoError::new_err(message),
+    }
+}
+
 /// Execute multiple agents in parallel from Python
+///
+/// `retry_budget` caps the retries of the whole batch, on top of each agent's
+/// `max_retries`.
 #[pyfunction]
+#[pyo3(signature = (agents, input_data, cancel_token=None, retry_budget=None))]
 fn execute_agents_parallel(
     py: Python<'_>,
     agents: Vec<(String, PyAgentConfig)>,
     input_data: String,
+    cancel_token: Option<PyCancellationToken>,
+    retry_budget: Option<usize>,
 ) -> PyResult<Bound<'_, PyAny>> {
-    let rust_agents: Vec<RustAgent> = agents
+    let cancel = cancel_token.unwrap_or_default().token;
+    let mut rust_agents: Vec<RustAgent> = agents
         .into_iter()
-        .map(|(id, mut config)| {
-            config.name = id;
-            RustAgent::new(config.into())
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
-        })
+        .map(|(id, config)| build_agent(id, config))
         .collect::<PyResult<Vec<RustAgent>>>()?;
+    if let Some(retry_budget) = retry_budget {
+        rust_agents = agent_runtime::share_retry_budget(rust_agents, retry_budget);
+    }
 
     pyo3_async_runtimes::tokio::future_into_py(py, async move {
         let tasks = vec![input_data; rust_agents.len()];
-        let results = execute_parallel(rust_agents, tasks)
+        let results = execute_parallel_with_cancel(rust_agents, tasks, cancel)
+            .await
+            .map_err(agent_err)?;
+        let py_results: Vec<PyAgentResult> = results.into_iter().map(PyAgentResult::from).collect();
+        Ok(py_results)
+    })
+}
+
+/// Execute agents concurrently, each with its own input
+///
+/// Every entry is an `(agent_id, config, input)` triple, so agents and inputs
+/// always line up one-to-one; an entry that isn't a three-element tuple raises
+/// `TypeError`. Results are returned in the same order as the entries.
+#[pyfunction]
+fn execute_agents_mapped(
+    py: Python<'_>,
+    agent_task_pairs: Vec<(String, PyAgentConfig, String)>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let mut rust_agents = Vec::with_capacity(agent_task_pairs.len());
+    let mut tasks = Vec::with_capacity(agent_task_pairs.len());
+    for (id, config, input) in agent_task_pairs {
+        rust_agents.push(build_agent(id, config)?);
+        tasks.push(input);
+    }
+
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        let results = execute_parallel_ordered(rust_agents, tasks)
             .await
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+            .map_err(agent_err)?;
         let py_results: Vec<PyAgentResult> = results.into_iter().map(PyAgentResult::from).collect();
         Ok(py_results)
     })
@@ -108,64 +607,681 @@ fn execute_agents_parallel(
 
 /// Execute a single agent from Python
 #[pyfunction]
+#[pyo3(signature = (agent_id, config, input_data, cancel_token=None))]
 fn execute_agent(
     py: Python<'_>,
     agent_id: String,
-    mut config: PyAgentConfig,
+    config: PyAgentConfig,
+    input_data: String,
+    cancel_token: Option<PyCancellationToken>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let agent = build_agent(agent_id, config)?;
+    let cancel = cancel_token.unwrap_or_default().token;
+
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        let result = agent
+            .execute_with_cancel(&input_data, &cancel)
+            .await
+            .map_err(agent_err)?;
+        Ok(PyAgentResult::from(result))
+    })
+}
+
+/// Execute a single agent with image attachments (`bytes` objects) for a
+/// vision model
+#[pyfunction]
+fn execute_agent_with_images(
+    py: Python<'_>,
+    agent_id: String,
+    config: PyAgentConfig,
     input_data: String,
+    images: Vec<Vec<u8>>,
 ) -> PyResult<Bound<'_, PyAny>> {
-    config.name = agent_id;
-    let agent = RustAgent::new(config.into())
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    let agent = build_agent(agent_id, config)?;
 
     pyo3_async_runtimes::tokio::future_into_py(py, async move {
         let result = agent
-            .execute(&input_data)
+            .execute_with_images(&input_data, images)
             .await
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+            .map_err(agent_err)?;
         Ok(PyAgentResult::from(result))
     })
 }
 
-/// Batch execute agents with different inputs
+/// Whether the backend in `config` is reachable and serves its model.
+/// Resolves to `False` rather than raising when the server can't be reached.
 #[pyfunction]
+fn check_backend(py: Python<'_>, config: PyAgentConfig) -> PyResult<Bound<'_, PyAny>> {
+    let agent = build_agent(config.name.clone(), config)?;
+
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        Ok(agent.health_check().await.unwrap_or(false))
+    })
+}
+
+/// Wait until the server at `ollama_url` is reachable and serves `model`,
+/// checking every `poll_interval_seconds`. Raises `AgentTimeoutError` once
+/// `timeout_seconds` have passed without success.
+#[pyfunction]
+#[pyo3(signature = (ollama_url, model, timeout_seconds, poll_interval_seconds=1.0))]
+fn wait_until_ready(
+    py: Python<'_>,
+    ollama_url: String,
+    model: String,
+    timeout_seconds: f64,
+    poll_interval_seconds: f64,
+) -> PyResult<Bound<'_, PyAny>> {
+    let seconds = |name: &str, value: f64| {
+        std::time::Duration::try_from_secs_f64(value).map_err(|_| {
+            agent_err(AgentError::Config(format!(
+                "{name} must be a non-negative number of seconds, got {value}"
+            )))
+        })
+    };
+    let timeout = seconds("timeout_seconds", timeout_seconds)?;
+    let poll_interval = seconds("poll_interval_seconds", poll_interval_seconds)?;
+
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        crate::wait_until_ready(&ollama_url, &model, timeout, poll_interval)
+            .await
+            .map_err(agent_err)
+    })
+}
+
+// Chunks buffered ahead of the Python consumer before the HTTP body stops
+// being read
+const STREAM_BUFFER: usize = 16;
+
+/// One shared HTTP client for many executions, so agents reuse keep-alive
+/// connections. Use as `with AgentPool(ollama_url) as pool:`; every config
+/// run through the pool is pointed at `ollama_url` when one is given. The
+/// client is released on exit, after which the pool can't be used.
+#[pyclass(name = "AgentPool")]
+pub struct PyAgentPool {
+    pool: Option<AgentPool>,
+    ollama_url: Option<String>,
+}
+
+impl PyAgentPool {
+    fn agent(&self, config: PyAgentConfig) -> PyResult<RustAgent> {
+        let pool = self
+            .pool
+            .as_ref()
+            .ok_or_else(|| PyRuntimeError::new_err("AgentPool is closed"))?;
+        let mut config: AgentConfig = config.try_into()?;
+        if let Some(url) = &self.ollama_url {
+            config.ollama_url = url.clone();
+        }
+        Ok(pool.agent(config))
+    }
+}
+
+#[pymethods]
+impl PyAgentPool {
+    #[new]
+    #[pyo3(signature = (ollama_url=None))]
+    fn new(ollama_url: Option<String>) -> PyResult<Self> {
+        Ok(Self {
+            pool: Some(AgentPool::new().map_err(agent_err)?),
+            ollama_url,
+        })
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (_exc_type=None, _exc_value=None, _traceback=None))]
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<Bound<'_, PyAny>>,
+        _exc_value: Option<Bound<'_, PyAny>>,
+        _traceback: Option<Bound<'_, PyAny>>,
+    ) -> bool {
+        self.close();
+        false
+    }
+
+    /// Drops the shared client. Executions already started keep their
+    /// connections until they finish.
+    fn close(&mut self) {
+        self.pool = None;
+    }
+
+    /// Execute a single agent; the agent id is the config's `name`
+    fn execute<'py>(
+        &self,
+        py: Python<'py>,
+        config: PyAgentConfig,
+        input_data: String,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let agent = self.agent(config)?;
+
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let result = agent.execute(&input_data).await.map_err(agent_err)?;
+            Ok(PyAgentResult::from(result))
+        })
+    }
+
+    /// Execute `configs[i]` on `inputs[i]` concurrently; results are
+    /// returned in input order
+    fn execute_many<'py>(
+        &self,
+        py: Python<'py>,
+        configs: Vec<PyAgentConfig>,
+        inputs: Vec<String>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        if configs.len() != inputs.len() {
+            return Err(ConfigError::new_err(format!(
+                "got {} configs and {} inputs; lengths must match",
+                configs.len(),
+                inputs.len()
+            )));
+        }
+        let agents = configs
+            .into_iter()
+            .map(|config| self.agent(config))
+            .collect::<PyResult<Vec<RustAgent>>>()?;
+
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let results = execute_parallel_ordered(agents, inputs)
+                .await
+                .map_err(agent_err)?;
+            let py_results: Vec<PyAgentResult> =
+                results.into_iter().map(PyAgentResult::from).collect();
+            Ok(py_results)
+        })
+    }
+}
+
+/// A multi-turn chat with one agent that keeps the history between calls.
+/// `send` returns the reply and records the exchange if it completed; only
+/// one `send` may be in flight at a time.
+#[pyclass(name = "Session")]
+pub struct PySession {
+    session: Arc<tokio::sync::Mutex<Session>>,
+}
+
+impl PySession {
+    fn lock(&self) -> PyResult<tokio::sync::MutexGuard<'_, Session>> {
+        self.session
+            .try_lock()
+            .map_err(|_| PyRuntimeError::new_err("session is busy with another send"))
+    }
+}
+
+#[pymethods]
+impl PySession {
+    #[new]
+    fn new(config: PyAgentConfig) -> PyResult<Self> {
+        let session = Session::new(config.try_into()?).map_err(agent_err)?;
+        Ok(Self {
+            session: Arc::new(tokio::sync::Mutex::new(session)),
+        })
+    }
+
+    /// Send the next user message and return the assistant's reply
+    fn send<'py>(&self, py: Python<'py>, message: String) -> PyResult<Bound<'py, PyAny>> {
+        let session = Arc::clone(&self.session);
+
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let mut session = session
+                .try_lock()
+                .map_err(|_| PyRuntimeError::new_err("session is busy with another send"))?;
+            let result = session.send(&message).await.map_err(agent_err)?;
+            Ok(PyAgentResult::from(result))
+        })
+    }
+
+    /// The conversation so far as `{"role": ..., "content": ...}` dicts
+    fn history(&self) -> PyResult<Vec<HashMap<String, String>>> {
+        Ok(self
+            .lock()?
+            .history()
+            .iter()
+            .map(|message| {
+                HashMap::from([
+                    ("role".to_string(), message.role.clone()),
+                    ("content".to_string(), message.content.clone()),
+                ])
+            })
+            .collect())
+    }
+
+    fn reset(&self) -> PyResult<()> {
+        self.lock()?.reset();
+        Ok(())
+    }
+}
+
+/// Async iterator over the chunks of a streaming execution. The request is
+/// driven by a background task that pauses once `STREAM_BUFFER` chunks are
+/// waiting, so a slow consumer applies backpressure; dropping the iterator
+/// aborts the request.
+#[pyclass(name = "AgentStream")]
+pub struct PyAgentStream {
+    chunks: Arc<tokio::sync::Mutex<mpsc::Receiver<Result<StreamEvent, AgentError>>>>,
+}
+
+/// Live throughput of a stream, yielded between chunks when
+/// `execute_agent_stream` is given a `progress_interval_ms`
+#[pyclass(name = "StreamProgress")]
+#[derive(Clone)]
+pub struct PyStreamProgress {
+    #[pyo3(get)]
+    pub tokens_so_far: u64,
+    #[pyo3(get)]
+    pub elapsed_ms: u64,
+    #[pyo3(get)]
+    pub tokens_per_sec: f64,
+}
+
+// What `AgentStream` yields: a `str` chunk or a `StreamProgress`
+#[derive(IntoPyObject)]
+enum PyStreamItem {
+    Chunk(String),
+    Progress(PyStreamProgress),
+}
+
+impl From<StreamEvent> for PyStreamItem {
+    fn from(event: StreamEvent) -> Self {
+        match event {
+            StreamEvent::Chunk(text) => PyStreamItem::Chunk(text),
+            StreamEvent::Progress(progress) => PyStreamItem::Progress(PyStreamProgress {
+                tokens_so_far: progress.tokens_so_far,
+                elapsed_ms: progress.elapsed_ms,
+                tokens_per_sec: progress.tokens_per_sec,
+            }),
+        }
+    }
+}
+
+#[pymethods]
+impl PyAgentStream {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let chunks = Arc::clone(&self.chunks);
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            match chunks.lock().await.recv().await {
+                Some(event) => event.map(PyStreamItem::from).map_err(agent_err),
+                None => Err(PyStopAsyncIteration::new_err(())),
+            }
+        })
+    }
+}
+
+/// Stream a single agent's output from Python
+///
+/// Returns an async iterator: `async for chunk in execute_agent_stream(config,
+/// input)` yields each text chunk as Ollama produces it and stops after the
+/// final `done` line. Request failures are raised from the iteration. With
+/// `progress_interval_ms`, a `StreamProgress` carrying the live token rate is
+/// also yielded about that often, between chunks.
+#[pyfunction]
+#[pyo3(signature = (config, input_data, progress_interval_ms=None))]
+fn execute_agent_stream(
+    config: PyAgentConfig,
+    input_data: String,
+    progress_interval_ms: Option<u64>,
+) -> PyResult<PyAgentStream> {
+    let agent = build_agent(config.name.clone(), config)?;
+    let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+
+    pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+        match progress_interval_ms {
+            None => match agent.execute_stream(&input_data).await {
+                Ok(stream) => forward(stream.map(|chunk| chunk.map(StreamEvent::Chunk)), tx).await,
+                Err(e) => {
+                    let _ = tx.send(Err(e)).await;
+                },
+            },
+            Some(interval_ms) => {
+                let interval = std::time::Duration::from_millis(interval_ms);
+                match agent
+                    .execute_stream_with_progress(&input_data, interval)
+                    .await
+                {
+                    Ok(stream) => forward(stream, tx).await,
+                    Err(e) => {
+                        let _ = tx.send(Err(e)).await;
+                    },
+                }
+            },
+        }
+    });
+
+    Ok(PyAgentStream {
+        chunks: Arc::new(tokio::sync::Mutex::new(rx)),
+    })
+}
+
+// Feeds `stream` to an `AgentStream`'s channel until either side is done
+async fn forward(
+    stream: impl futures::Stream<Item = Result<StreamEvent, AgentError>>,
+    tx: mpsc::Sender<Result<StreamEvent, AgentError>>,
+) {
+    futures::pin_mut!(stream);
+    while let Some(event) = stream.next().await {
+        // The receiver is gone once Python drops the iterator
+        if tx.send(event).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Async iterator over the merged chunks of several streaming agents; see
+/// `execute_agents_stream`.
+#[pyclass(name = "ParallelStream")]
+pub struct PyParallelStream {
+    chunks: Arc<tokio::sync::Mutex<mpsc::Receiver<TaggedChunk>>>,
+}
+
+type TaggedChunk = (String, Result<String, AgentError>);
+
+#[pymethods]
+impl PyParallelStream {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let chunks = Arc::clone(&self.chunks);
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            match chunks.lock().await.recv().await {
+                Some((id, chunk)) => chunk.map(|chunk| (id, chunk)).map_err(agent_err),
+                None => Err(PyStopAsyncIteration::new_err(())),
+            }
+        })
+    }
+}
+
+/// Stream several agents at once from Python
+///
+/// Every entry is an `(agent_id, config, input)` triple. `async for id, chunk
+/// in execute_agents_stream(entries)` yields each chunk as soon as any agent
+/// produces it, tagged with that agent's id. A request failure is raised
+/// from the iteration, which ends it for every agent.
+#[pyfunction]
+fn execute_agents_stream(
+    agent_task_pairs: Vec<(String, PyAgentConfig, String)>,
+) -> PyResult<PyParallelStream> {
+    let mut rust_agents = Vec::with_capacity(agent_task_pairs.len());
+    let mut tasks = Vec::with_capacity(agent_task_pairs.len());
+    for (id, config, input) in agent_task_pairs {
+        rust_agents.push(build_agent(id, config)?);
+        tasks.push(input);
+    }
+    let stream = execute_parallel_stream(rust_agents, tasks).map_err(agent_err)?;
+    let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+
+    pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+        futures::pin_mut!(stream);
+        while let Some(item) = stream.next().await {
+            if tx.send(item).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    Ok(PyParallelStream {
+        chunks: Arc::new(tokio::sync::Mutex::new(rx)),
+    })
+}
+
+/// Execute a single agent over the streaming API and return the gathered
+/// result, including `first_token_ms`
+#[pyfunction]
+fn execute_agent_streamed(
+    py: Python<'_>,
+    agent_id: String,
+    config: PyAgentConfig,
+    input_data: String,
+) -> PyResult<Bound<'_, PyAny>> {
+    let agent = build_agent(agent_id, config)?;
+
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        let result = agent
+            .execute_streamed(&input_data)
+            .await
+            .map_err(agent_err)?;
+        Ok(PyAgentResult::from(result))
+    })
+}
+
+/// Embed several texts concurrently with one agent configuration
+#[pyfunction]
+fn get_embeddings(
+    py: Python<'_>,
+    config: PyAgentConfig,
+    texts: Vec<String>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let agent = build_agent(config.name.clone(), config)?;
+
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        let embeddings = futures::future::try_join_all(texts.iter().map(|text| agent.embed(text)))
+            .await
+            .map_err(agent_err)?;
+        Ok(embeddings)
+    })
+}
+
+/// Batch execute agents with different inputs.
+///
+/// `progress`, if given, is called with each `PyAgentResult` as soon as that
+/// agent finishes. It runs on a tokio worker thread with the GIL held, one
+/// call at a time, so it must be thread-safe with respect to the caller's
+/// event loop (e.g. only update a progress bar) and should return quickly.
+/// Exceptions it raises are reported through `sys.unraisablehook` and do not
+/// stop the batch.
+///
+/// By default Ctrl-C under `asyncio.run` cancels the awaiting task;
+/// `pyo3_async_runtimes` then drops the batch, aborting every agent, and the
+/// results are lost. With `cancel_on_interrupt`, the batch instead swaps in
+/// its own `SIGINT` handler while it runs: the first Ctrl-C fires the
+/// cancellation token, agents still running finish as `"cancelled"`, and the
+/// batch returns normally with the partial results. A second Ctrl-C is passed
+/// to the previous handler, so it still interrupts. The handler runs on
+/// Python's main thread with the GIL, like any Python signal handler, and
+/// only fires the token, which tokio workers observe without the GIL. Must be
+/// called from the thread running the event loop, normally the main thread.
+#[pyfunction]
+#[pyo3(signature = (agent_configs, inputs, cancel_token=None, progress=None, cancel_on_interrupt=false))]
 fn execute_agents_batch(
     py: Python<'_>,
     agent_configs: Vec<(String, PyAgentConfig)>,
     inputs: Vec<String>,
+    cancel_token: Option<PyCancellationToken>,
+    progress: Option<Py<PyAny>>,
+    cancel_on_interrupt: bool,
 ) -> PyResult<Bound<'_, PyAny>> {
+    let cancel = cancel_token.unwrap_or_default().token;
+    let interrupt_guard = cancel_on_interrupt
+        .then(|| InterruptGuard::install(py, cancel.clone()))
+        .transpose()?;
+
     pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        let _interrupt_guard = interrupt_guard;
         let mut all_results = Vec::new();
 
-        for input in inputs {
+        for (input_index, input) in inputs.into_iter().enumerate() {
             let rust_agents: Vec<RustAgent> = agent_configs
                 .iter()
-                .map(|(id, config)| {
-                    let mut config = config.clone();
-                    config.name = id.clone();
-                    RustAgent::new(config.into()).map_err(|e| {
-                        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string())
-                    })
-                })
+                .map(|(id, config)| build_agent(id.clone(), config.clone()))
                 .collect::<PyResult<Vec<RustAgent>>>()?;
 
             let tasks = vec![input; rust_agents.len()];
-            let results = execute_parallel(rust_agents, tasks)
+            let results =
+                execute_parallel_with_progress(rust_agents, tasks, cancel.clone(), |result| {
+                    if let Some(callback) = &progress {
+                        report_progress(callback, result);
+                    }
+                })
                 .await
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
-            all_results.extend(results.into_iter().map(PyAgentResult::from));
+                .map_err(agent_err)?;
+            all_results.extend(
+                results
+                    .into_iter()
+                    .map(|result| PyAgentResult::from(result.for_task(input_index))),
+            );
         }
 
         Ok(all_results)
     })
 }
 
+// Python's `SIGINT` handler, replaced for the life of a batch by one that
+// fires the batch's token. Dropping the guard restores the previous handler,
+// scheduled on the event loop because `signal.signal` only works on the main
+// thread and the batch ends on a tokio worker.
+struct InterruptGuard {
+    event_loop: Py<PyAny>,
+    previous: Py<PyAny>,
+}
+
+impl InterruptGuard {
+    fn install(py: Python<'_>, cancel: CancellationToken) -> PyResult<Self> {
+        let signal = py.import("signal")?;
+        let sigint = signal.getattr("SIGINT")?;
+        let previous = signal.call_method1("getsignal", (&sigint,))?.unbind();
+        let forward = previous.clone_ref(py);
+
+        let handler = PyCFunction::new_closure(py, None, None, move |args, _kwargs| {
+            if !cancel.is_cancelled() {
+                cancel.cancel();
+                return Ok(());
+            }
+            let forward = forward.bind(args.py());
+            if forward.is_callable() {
+                forward.call1(args.clone()).map(drop)
+            } else {
+                Err(PyKeyboardInterrupt::new_err(()))
+            }
+        })?;
+        let event_loop = py
+            .import("asyncio")?
+            .call_method0("get_running_loop")?
+            .unbind();
+        signal.call_method1("signal", (&sigint, handler))?;
+
+        Ok(Self {
+            event_loop,
+            previous,
+        })
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        Python::attach(|py| {
+            let restore = || -> PyResult<()> {
+                let signal = py.import("signal")?;
+                self.event_loop.call_method1(
+                    py,
+                    "call_soon_threadsafe",
+                    (
+                        signal.getattr("signal")?,
+                        signal.getattr("SIGINT")?,
+                        &self.previous,
+                    ),
+                )?;
+                Ok(())
+            };
+            // The loop may already be closed if the batch outlived it
+            if let Err(e) = restore() {
+                e.write_unraisable(py, None);
+            }
+        });
+    }
+}
+
+// Calls the Python progress callback, reporting rather than propagating any
+// exception it raises
+fn report_progress(callback: &Py<PyAny>, result: &agent_runtime::AgentResult) {
+    Python::attach(|py| {
+        if let Err(e) = callback.call1(py, (PyAgentResult::from(result.clone()),)) {
+            e.write_unraisable(py, Some(callback.bind(py)));
+        }
+    });
+}
+
+/// A model installed on an Ollama server
+#[pyclass(name = "ModelInfo")]
+#[derive(Clone)]
+pub struct PyModelInfo {
+    #[pyo3(get)]
+    pub name: String,
+    /// Size on disk in bytes
+    #[pyo3(get)]
+    pub size: u64,
+    /// RFC 3339 timestamp of the last pull or modification
+    #[pyo3(get)]
+    pub modified_at: String,
+}
+
+impl From<ModelInfo> for PyModelInfo {
+    fn from(info: ModelInfo) -> Self {
+        Self {
+            name: info.name,
+            size: info.size,
+            modified_at: info.modified_at,
+        }
+    }
+}
+
+/// List the models installed on the Ollama server at `ollama_url`; raises
+/// `AgentTimeoutError` if it does not answer within the default agent timeout
+#[pyfunction]
+fn list_models(py: Python<'_>, ollama_url: String) -> PyResult<Bound<'_, PyAny>> {
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        let models = crate::list_models(&ollama_url).await.map_err(agent_err)?;
+        let py_models: Vec<PyModelInfo> = models.into_iter().map(PyModelInfo::from).collect();
+        Ok(py_models)
+    })
+}
+
+/// Write results to `path` as a pretty-printed JSON array
+#[pyfunction]
+fn write_results(results: Vec<PyAgentResult>, path: &str) -> PyResult<()> {
+    let results: Vec<agent_runtime::AgentResult> = results.into_iter().map(Into::into).collect();
+    crate::write_results(&results, path).map_err(agent_err)
+}
+
+/// Read results written by `write_results`
+#[pyfunction]
+fn read_results(path: &str) -> PyResult<Vec<PyAgentResult>> {
+    let results = crate::read_results(path).map_err(agent_err)?;
+    Ok(results.into_iter().map(PyAgentResult::from).collect())
+}
+
+/// Render a `{var}` prompt template with values from `vars`. Raises
+/// `ConfigError` naming any placeholder without a value.
+#[pyfunction]
+fn render_prompt(template: &str, vars: HashMap<String, String>) -> PyResult<String> {
+    PromptTemplate::new(template)
+        .render(&vars)
+        .map_err(agent_err)
+}
+
+/// Rough token count of `text` (about four characters per token), for
+/// checking prompts against `num_ctx` without a request. Only an estimate.
+#[pyfunction]
+fn estimate_tokens(text: &str) -> usize {
+    crate::estimate_tokens(text)
+}
+
 /// Performance metrics for agent execution
 #[pyclass]
 #[derive(Clone)]
 pub struct PyExecutionMetrics {
+    /// Number of results, i.e. executions
     #[pyo3(get)]
     pub total_agents: usize,
+    /// Number of distinct `agent_id`s among the results
+    #[pyo3(get)]
+    pub distinct_agents: usize,
     #[pyo3(get)]
     pub successful: usize,
     #[pyo3(get)]
@@ -174,13 +1290,51 @@ pub struct PyExecutionMetrics {
     pub total_time: f64,
     #[pyo3(get)]
     pub avg_time: f64,
+    #[pyo3(get)]
+    pub min_time: f64,
+    #[pyo3(get)]
+    pub max_time: f64,
+    #[pyo3(get)]
+    pub median_time: f64,
+    #[pyo3(get)]
+    pub p95_time: f64,
+    #[pyo3(get)]
+    pub p99_time: f64,
 }
 
-/// Get execution metrics from results
+#[pymethods]
+impl PyExecutionMetrics {
+    /// Every field as a plain dict
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("total_agents", self.total_agents)?;
+        dict.set_item("distinct_agents", self.distinct_agents)?;
+        dict.set_item("successful", self.successful)?;
+        dict.set_item("failed", self.failed)?;
+        dict.set_item("total_time", self.total_time)?;
+        dict.set_item("avg_time", self.avg_time)?;
+        dict.set_item("min_time", self.min_time)?;
+        dict.set_item("max_time", self.max_time)?;
+        dict.set_item("median_time", self.median_time)?;
+        dict.set_item("p95_time", self.p95_time)?;
+        dict.set_item("p99_time", self.p99_time)?;
+        Ok(dict)
+    }
+}
+
+/// Get execution metrics from results, including latency percentiles
 #[pyfunction]
 fn get_metrics(results: Vec<PyAgentResult>) -> PyExecutionMetrics {
     let total_agents = results.len();
-    let successful = results.iter().filter(|r| r.status == "completed").count();
+    let distinct_agents = results
+        .iter()
+        .map(|r| r.agent_id.as_str())
+        .collect::<HashSet<_>>()
+        .len();
+    let successful = results
+        .iter()
+        .filter(|r| matches!(r.status, AgentStatus::Completed))
+        .count();
     let failed = total_agents - successful;
     let total_time: f64 = results.iter().map(|r| r.execution_time).sum();
     let avg_time = if total_agents > 0 {
@@ -188,25 +1342,175 @@ fn get_metrics(results: Vec<PyAgentResult>) -> PyExecutionMetrics {
     } else {
         0.0
     };
+    let durations: Vec<f64> = results.iter().map(|r| r.execution_time).collect();
+    let latency = LatencyStats::from_durations(&durations);
 
     PyExecutionMetrics {
         total_agents,
+        distinct_agents,
         successful,
         failed,
         total_time,
         avg_time,
+        min_time: latency.min,
+        max_time: latency.max,
+        median_time: latency.p50,
+        p95_time: latency.p95,
+        p99_time: latency.p99,
     }
 }
 
+/// Token throughput over a result set
+#[pyclass]
+#[derive(Clone)]
+pub struct PyThroughputReport {
+    #[pyo3(get)]
+    pub total_prompt_tokens: u64,
+    #[pyo3(get)]
+    pub total_completion_tokens: u64,
+    #[pyo3(get)]
+    pub tokens_per_second: f64,
+    #[pyo3(get)]
+    pub wall_clock_ms: f64,
+    #[pyo3(get)]
+    pub summed_duration_ms: f64,
+    #[pyo3(get)]
+    pub parallelism: f64,
+}
+
+#[pymethods]
+impl PyThroughputReport {
+    /// Rough cost given per-1000-token prices
+    fn estimated_cost(&self, prompt_per_1k: f64, completion_per_1k: f64) -> f64 {
+        ThroughputReport::from(self.clone()).estimated_cost(prompt_per_1k, completion_per_1k)
+    }
+}
+
+impl From<ThroughputReport> for PyThroughputReport {
+    fn from(report: ThroughputReport) -> Self {
+        PyThroughputReport {
+            total_prompt_tokens: report.total_prompt_tokens,
+            total_completion_tokens: report.total_completion_tokens,
+            tokens_per_second: report.tokens_per_second,
+            wall_clock_ms: report.wall_clock_ms,
+            summed_duration_ms: report.summed_duration_ms,
+            parallelism: report.parallelism,
+        }
+    }
+}
+
+impl From<PyThroughputReport> for ThroughputReport {
+    fn from(report: PyThroughputReport) -> Self {
+        ThroughputReport {
+            total_prompt_tokens: report.total_prompt_tokens,
+            total_completion_tokens: report.total_completion_tokens,
+            tokens_per_second: report.tokens_per_second,
+            wall_clock_ms: report.wall_clock_ms,
+            summed_duration_ms: report.summed_duration_ms,
+            parallelism: report.parallelism,
+        }
+    }
+}
+
+/// Get token throughput from results. Pass the batch's measured
+/// `wall_time_ms`; without it the longest single execution is used.
+#[pyfunction]
+#[pyo3(signature = (results, wall_time_ms=None))]
+fn get_throughput(results: Vec<PyAgentResult>, wall_time_ms: Option<f64>) -> PyThroughputReport {
+    let report = ThroughputReport::from_usage(
+        results
+            .iter()
+            .map(|r| (r.prompt_tokens, r.completion_tokens, r.execution_time)),
+        wall_time_ms,
+    );
+    PyThroughputReport::from(report)
+}
+
+/// Split results into a dict keyed by status string (`"completed"`,
+/// `"failed"`, `"timed_out"`, `"cancelled"`); every key is present and each
+/// list keeps the input order
+#[pyfunction]
+fn partition_results(results: Vec<PyAgentResult>) -> HashMap<&'static str, Vec<PyAgentResult>> {
+    let mut partition: HashMap<&'static str, Vec<PyAgentResult>> = [
+        AgentStatus::Completed,
+        AgentStatus::Failed,
+        AgentStatus::TimedOut,
+        AgentStatus::Cancelled,
+    ]
+    .into_iter()
+    .map(|status| (status.as_str(), Vec::new()))
+    .collect();
+    for result in results {
+        partition
+            .entry(result.status.as_str())
+            .or_default()
+            .push(result);
+    }
+    partition
+}
+
+/// A copy of `results` ordered by execution time, slowest first when
+/// `descending`; ties keep their relative order
+#[pyfunction]
+#[pyo3(signature = (results, descending=false))]
+fn sort_results_by_duration(
+    mut results: Vec<PyAgentResult>,
+    descending: bool,
+) -> Vec<PyAgentResult> {
+    results.sort_by(|a, b| {
+        let order = a.execution_time.total_cmp(&b.execution_time);
+        if descending {
+            order.reverse()
+        } else {
+            order
+        }
+    });
+    results
+}
+
 /// Python module definition
 #[pymodule]
 fn agent_runtime_py(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(execute_agents_parallel, m)?)?;
+    m.add_function(wrap_pyfunction!(execute_agents_mapped, m)?)?;
     m.add_function(wrap_pyfunction!(execute_agent, m)?)?;
+    m.add_function(wrap_pyfunction!(execute_agent_stream, m)?)?;
+    m.add_function(wrap_pyfunction!(execute_agents_stream, m)?)?;
+    m.add_function(wrap_pyfunction!(execute_agent_streamed, m)?)?;
+    m.add_function(wrap_pyfunction!(execute_agent_with_images, m)?)?;
     m.add_function(wrap_pyfunction!(execute_agents_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(get_embeddings, m)?)?;
     m.add_function(wrap_pyfunction!(get_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(get_throughput, m)?)?;
+    m.add_function(wrap_pyfunction!(partition_results, m)?)?;
+    m.add_function(wrap_pyfunction!(sort_results_by_duration, m)?)?;
+    m.add_function(wrap_pyfunction!(render_prompt, m)?)?;
+    m.add_function(wrap_pyfunction!(estimate_tokens, m)?)?;
+    m.add_function(wrap_pyfunction!(check_backend, m)?)?;
+    m.add_function(wrap_pyfunction!(wait_until_ready, m)?)?;
+    m.add_function(wrap_pyfunction!(list_models, m)?)?;
+    m.add_function(wrap_pyfunction!(write_results, m)?)?;
+    m.add_function(wrap_pyfunction!(read_results, m)?)?;
     m.add_class::<PyAgentConfig>()?;
+    m.add_class::<PyCancellationToken>()?;
     m.add_class::<PyAgentResult>()?;
+    m.add_class::<PyAgentPool>()?;
+    m.add_class::<PyAgentStream>()?;
+    m.add_class::<PyParallelStream>()?;
+    m.add_class::<PyStreamProgress>()?;
     m.add_class::<PyExecutionMetrics>()?;
+    m.add_class::<PyModelInfo>()?;
+    m.add_class::<PySession>()?;
+    m.add_class::<PyThroughputReport>()?;
+    m.add("AgentTimeoutError", m.py().get_type::<AgentTimeoutError>())?;
+    m.add(
+        "AgentConnectionError",
+        m.py().get_type::<AgentConnectionError>(),
+    )?;
+    m.add("HttpStatusError", m.py().get_type::<HttpStatusError>())?;
+    m.add("DeserializeError", m.py().get_type::<DeserializeError>())?;
+    m.add("ConfigError", m.py().get_type::<ConfigError>())?;
+    m.add("BackendError", m.py().get_type::<BackendError>())?;
+    m.add("AgentIoError", m.py().get_type::<AgentIoError>())?;
     Ok(())
 }
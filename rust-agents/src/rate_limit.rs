@@ -0,0 +1,97 @@
+// Per-backend request pacing so bursts of agents don't overload one Ollama
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+/// Slowest rate `AgentConfig::validate` accepts: one request a day. Slower
+/// rates would space requests further apart than a `Duration` or `Instant`
+/// can represent.
+pub(crate) const MIN_REQUESTS_PER_SECOND: f64 = 1.0 / (24.0 * 60.0 * 60.0);
+
+/// Whether `requests_per_second` is a rate a limiter can pace.
+pub(crate) fn is_valid_rate(requests_per_second: f64) -> bool {
+    requests_per_second.is_finite() && requests_per_second >= MIN_REQUESTS_PER_SECOND
+}
+
+/// Token bucket holding a single token: request starts are spaced at least
+/// `interval` apart, with no bursting beyond that.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(requests_per_second: f64) -> Self {
+        // Out-of-range rates are rejected by `validate`; pace them as slowly
+        // as allowed rather than panicking
+        let interval = Duration::try_from_secs_f64(1.0 / requests_per_second)
+            .unwrap_or(Duration::MAX)
+            .min(Duration::from_secs_f64(1.0 / MIN_REQUESTS_PER_SECOND));
+        Self {
+            interval,
+            next_slot: Mutex::new(None),
+        }
+    }
+
+    /// Waits until the caller may start a request. Slots are handed out in
+    /// call order, so concurrent callers queue rather than race.
+    pub(crate) async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let slot = next_slot.map_or(now, |next| next.max(now));
+            *next_slot = Some(slot + self.interval);
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+// One limiter per `ollama_url`, shared by every agent in the process
+static LIMITERS: OnceLock<Mutex<HashMap<String, Arc<RateLimiter>>>> = OnceLock::new();
+
+/// The shared limiter for `url`. The first caller's rate wins; agents
+/// pointing at the same backend are expected to agree on it.
+pub(crate) fn limiter_for(url: &str, requests_per_second: f64) -> Arc<RateLimiter> {
+    let mut limiters = LIMITERS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    limiters
+        .entry(url.to_string())
+        .or_insert_with(|| Arc::new(RateLimiter::new(requests_per_second)))
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_acquire_spaces_request_starts() {
+        let limiter = RateLimiter::new(50.0);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            limiter.acquire().await;
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[test]
+    fn test_tiny_rates_are_rejected_and_clamped() {
+        assert!(!is_valid_rate(1e-300));
+        assert!(!is_valid_rate(f64::NAN));
+        assert!(is_valid_rate(MIN_REQUESTS_PER_SECOND));
+
+        let limiter = RateLimiter::new(1e-300);
+        assert_eq!(limiter.interval, Duration::from_secs(24 * 60 * 60));
+    }
+}
@@ -0,0 +1,121 @@
+// Saving and reloading batch results as a single JSON file
+
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::{
+    agent_runtime::AgentResult,
+    error::{AgentError, Result},
+};
+
+/// Writes `results` to `path` as a pretty-printed JSON array, replacing any
+/// existing file.
+pub fn write_results(results: &[AgentResult], path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let json = serde_json::to_string_pretty(results)?;
+    std::fs::write(path, json).map_err(|e| {
+        AgentError::Io(format!(
+            "failed to write results to {}: {e}",
+            path.display()
+        ))
+    })
+}
+
+/// Reads back a file written by [`write_results`], by this or any earlier
+/// version: records with an older [`AgentResult::schema_version`] are
+/// upgraded to the current one. Records from a newer version are rejected.
+pub fn read_results(path: impl AsRef<Path>) -> Result<Vec<AgentResult>> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        AgentError::Io(format!(
+            "failed to read results from {}: {e}",
+            path.display()
+        ))
+    })?;
+    let invalid =
+        |e: &dyn std::fmt::Display| AgentError::Deserialize(format!("{}: {e}", path.display()));
+
+    let records: Vec<Value> = serde_json::from_str(&contents).map_err(|e| invalid(&e))?;
+    records
+        .into_iter()
+        .map(|record| {
+            let record = upgrade(record).map_err(|e| invalid(&e))?;
+            serde_json::from_value(record).map_err(|e| invalid(&e))
+        })
+        .collect()
+}
+
+// Brings one serialized result up to `AgentResult::SCHEMA_VERSION`. The later
+// fields all have serde defaults, so only `run_id` needs filling in.
+fn upgrade(mut record: Value) -> std::result::Result<Value, String> {
+    let version = record
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .unwrap_or(1);
+    if version > u64::from(AgentResult::SCHEMA_VERSION) {
+        return Err(format!(
+            "schema_version {version} is newer than the supported {}",
+            AgentResult::SCHEMA_VERSION
+        ));
+    }
+
+    if let Some(fields) = record.as_object_mut() {
+        // Version 1 predates run ids, so each old record gets a fresh one
+        if version < 2 {
+            fields
+                .entry("run_id")
+                .or_insert_with(|| serde_json::json!(uuid::Uuid::new_v4()));
+        }
+        fields.insert(
+            "schema_version".to_string(),
+            serde_json::json!(AgentResult::SCHEMA_VERSION),
+        );
+    }
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_runtime::AgentStatus;
+
+    #[test]
+    fn test_results_round_trip() {
+        let results = vec![AgentResult {
+            duration_ms: 1200,
+            prompt_tokens: Some(12),
+            completion_tokens: Some(5),
+            ..AgentResult::completed("coder", "fn main() {}")
+        }];
+        let path = std::env::temp_dir().join(format!("results-{}.json", uuid::Uuid::new_v4()));
+
+        write_results(&results, &path).unwrap();
+        let loaded = read_results(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].run_id, results[0].run_id);
+        assert_eq!(loaded[0].output, results[0].output);
+        assert_eq!(loaded[0].status, AgentStatus::Completed);
+    }
+
+    #[test]
+    fn test_version_1_records_are_upgraded() {
+        let path = std::env::temp_dir().join(format!("results-v1-{}.json", uuid::Uuid::new_v4()));
+        let v1 = r#"[{"agent_id": "coder", "status": "completed", "output": "ok",
+                      "error": null, "duration_ms": 1200}]"#;
+        std::fs::write(&path, v1).unwrap();
+
+        let loaded = read_results(&path).unwrap();
+        assert_eq!(loaded[0].agent_id, "coder");
+        assert_eq!(loaded[0].status, AgentStatus::Completed);
+        assert_eq!(loaded[0].schema_version, AgentResult::SCHEMA_VERSION);
+        assert!(!loaded[0].run_id.is_nil());
+
+        std::fs::write(&path, r#"[{"agent_id": "coder", "schema_version": 99}]"#).unwrap();
+        let newer = read_results(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(newer, Err(AgentError::Deserialize(_))));
+    }
+}
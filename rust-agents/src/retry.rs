@@ -0,0 +1,154 @@
+// Retry helpers for transient backend failures
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rand::{Rng, RngExt};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AgentError;
+
+/// Which failures an agent retries, up to `max_retries`. Choose `Never` or a
+/// narrow `Custom` predicate for requests with side effects, where a retry
+/// could repeat them.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryPolicy {
+    Never,
+    /// Timeouts and connection failures only
+    OnNetwork,
+    /// Timeouts, connection failures, and 5xx responses
+    #[default]
+    OnServerError,
+    /// Retry whenever the predicate returns `true`; a 5xx or other rejected
+    /// response is passed as [`AgentError::HttpStatus`]. Not serializable.
+    #[serde(skip)]
+    Custom(Arc<dyn Fn(&AgentError) -> bool + Send + Sync>),
+}
+
+impl RetryPolicy {
+    pub fn custom(should_retry: impl Fn(&AgentError) -> bool + Send + Sync + 'static) -> Self {
+        RetryPolicy::Custom(Arc::new(should_retry))
+    }
+
+    pub fn should_retry(&self, err: &AgentError) -> bool {
+        match self {
+            RetryPolicy::Never => false,
+            RetryPolicy::OnNetwork => err.is_retryable(),
+            RetryPolicy::OnServerError => {
+                err.is_retryable() || matches!(err, AgentError::HttpStatus(500..=599))
+            },
+            RetryPolicy::Custom(should_retry) => should_retry(err),
+        }
+    }
+
+    pub(crate) fn is_custom(&self) -> bool {
+        matches!(self, RetryPolicy::Custom(_))
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetryPolicy::Never => f.write_str("Never"),
+            RetryPolicy::OnNetwork => f.write_str("OnNetwork"),
+            RetryPolicy::OnServerError => f.write_str("OnServerError"),
+            RetryPolicy::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// A pool of retries shared by every agent of a batch, so the batch as a
+/// whole can't amplify load on a struggling backend beyond `n` extra requests.
+#[derive(Debug)]
+pub(crate) struct RetryBudget {
+    remaining: AtomicUsize,
+}
+
+impl RetryBudget {
+    pub(crate) fn new(retries: usize) -> Self {
+        Self {
+            remaining: AtomicUsize::new(retries),
+        }
+    }
+
+    /// Takes one retry from the budget; `false` once it is exhausted.
+    pub(crate) fn try_spend(&self) -> bool {
+        self.remaining
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1))
+            .is_ok()
+    }
+}
+
+/// Delay before retry number `attempt` (0-based): `base_ms * 2^attempt`,
+/// saturating rather than overflowing for large attempt counts.
+pub(crate) fn backoff_delay(base_ms: u64, attempt: u32) -> Duration {
+    let factor = 1u64.checked_shl(attempt).unwrap_or(u64::MAX);
+    Duration::from_millis(base_ms.saturating_mul(factor))
+}
+
+/// "Full jitter": a uniformly random delay between zero and `delay`, so
+/// agents that failed together don't all retry at the same instant.
+pub(crate) fn jittered(delay: Duration, rng: &mut impl Rng) -> Duration {
+    let millis = u64::try_from(delay.as_millis()).unwrap_or(u64::MAX);
+    Duration::from_millis(rng.random_range(0..=millis))
+}
+
+/// Whether a failure is worth retrying; see [`AgentError::is_retryable`].
+pub(crate) fn is_retryable_error(err: &AgentError) -> bool {
+    err.is_retryable()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_retry_policies_classify_errors() {
+        let server_error = AgentError::HttpStatus(503);
+        assert!(RetryPolicy::OnServerError.should_retry(&server_error));
+        assert!(RetryPolicy::OnServerError.should_retry(&AgentError::Timeout));
+        assert!(!RetryPolicy::OnServerError.should_retry(&AgentError::HttpStatus(400)));
+        assert!(!RetryPolicy::OnNetwork.should_retry(&server_error));
+        assert!(!RetryPolicy::Never.should_retry(&AgentError::Timeout));
+        assert!(RetryPolicy::custom(|e| *e == AgentError::HttpStatus(429))
+            .should_retry(&AgentError::HttpStatus(429)));
+    }
+
+    #[test]
+    fn test_retry_budget_is_spent_down_to_zero() {
+        let budget = RetryBudget::new(2);
+        assert!(budget.try_spend());
+        assert!(budget.try_spend());
+        assert!(!budget.try_spend());
+        assert!(!RetryBudget::new(0).try_spend());
+    }
+
+    #[test]
+    fn test_jittered_delays_stay_within_backoff() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let delays: Vec<Duration> = (0..6)
+            .map(|attempt| {
+                let cap = backoff_delay(100, attempt);
+                let delay = jittered(cap, &mut rng);
+                assert!(delay <= cap, "attempt {attempt}: {delay:?} > {cap:?}");
+                delay
+            })
+            .collect();
+
+        // Jitter actually spreads the delays rather than pinning them to the
+        // cap
+        assert!(delays
+            .iter()
+            .zip(0..)
+            .any(|(delay, attempt)| *delay < backoff_delay(100, attempt)));
+    }
+}
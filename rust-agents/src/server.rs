@@ -0,0 +1,232 @@
+// HTTP service wrapping `execute` and `execute_parallel` for non-Rust clients
+
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::net::TcpListener;
+
+use crate::{
+    agent_runtime::{execute_parallel, AgentConfig, AgentResult},
+    error::{AgentError, Result},
+    pool::AgentPool,
+};
+
+/// Body of `POST /execute`, and one entry of `POST /execute_parallel`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExecuteRequest {
+    pub config: AgentConfig,
+    pub prompt: String,
+}
+
+/// Body of `POST /execute_parallel`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ParallelRequest {
+    pub requests: Vec<ExecuteRequest>,
+}
+
+type Rejection = (StatusCode, Json<serde_json::Value>);
+
+#[derive(Clone)]
+struct Gateway {
+    pool: AgentPool,
+    ollama_urls: Arc<[String]>,
+}
+
+/// Routes `POST /execute` and `POST /execute_parallel`.
+///
+/// Clients are not authenticated, so they only choose what to run, never
+/// where or how it is sent: a config's `ollama_url` must be one of
+/// `ollama_urls` (compared without trailing slashes) or the request is
+/// rejected with `403`. Every agent is built on `pool`'s client, so requests
+/// share its connections and its connection-level settings; a client's
+/// `headers`, `bearer_token`, `user_agent`, `auto_pull`, proxy, TLS, and
+/// connection timeout and pool settings are ignored. So are the settings
+/// behind the per-backend state every client shares (`requests_per_second`,
+/// `failure_threshold`, `cooldown_ms`, `cache_capacity`, and
+/// `cache_ttl_seconds`) and `max_retries`: the defaults apply instead, so no
+/// request can throttle, trip, or flood a backend for the others.
+///
+/// Execution failures come back as `200` with a non-completed
+/// [`AgentResult`]; an invalid config is rejected with `400`. Rejections
+/// carry `{"error": ...}`.
+pub fn router(pool: AgentPool, ollama_urls: Vec<String>) -> Router {
+    let ollama_urls = ollama_urls
+        .into_iter()
+        .map(|url| url.trim_end_matches('/').to_string())
+        .collect();
+    Router::new()
+        .route("/execute", post(execute_one))
+        .route("/execute_parallel", post(execute_many))
+        .with_state(Gateway { pool, ollama_urls })
+}
+
+/// Serves [`router`] on `listener` until the process stops.
+pub async fn serve(listener: TcpListener, pool: AgentPool, ollama_urls: Vec<String>) -> Result<()> {
+    axum::serve(listener, router(pool, ollama_urls))
+        .await
+        .map_err(|e| AgentError::Io(format!("server failed: {e}")))
+}
+
+async fn execute_one(
+    State(gateway): State<Gateway>,
+    Json(request): Json<ExecuteRequest>,
+) -> std::result::Result<Json<AgentResult>, Rejection> {
+    let agent = gateway.pool.agent(gateway.admit(request.config)?);
+    Ok(Json(agent.execute_settled(&request.prompt).await))
+}
+
+async fn execute_many(
+    State(gateway): State<Gateway>,
+    Json(request): Json<ParallelRequest>,
+) -> std::result::Result<Json<Vec<AgentResult>>, Rejection> {
+    let (agents, prompts) = request
+        .requests
+        .into_iter()
+        .map(|r| Ok((gateway.pool.agent(gateway.admit(r.config)?), r.prompt)))
+        .collect::<std::result::Result<(Vec<_>, Vec<_>), Rejection>>()?;
+    execute_parallel(agents, prompts)
+        .await
+        .map(Json)
+        .map_err(|e| reject(StatusCode::BAD_REQUEST, e))
+}
+
+impl Gateway {
+    // A client's config with the settings it may not choose reset, once its
+    // server is allowed and it validates
+    fn admit(&self, config: AgentConfig) -> std::result::Result<AgentConfig, Rejection> {
+        let url = config.ollama_url.trim_end_matches('/');
+        if !self.ollama_urls.iter().any(|allowed| allowed == url) {
+            return Err(reject(
+                StatusCode::FORBIDDEN,
+                AgentError::Config(format!("ollama_url '{url}' is not served here")),
+            ));
+        }
+
+        let defaults = AgentConfig::default();
+        let config = AgentConfig {
+            headers: defaults.headers,
+            bearer_token: defaults.bearer_token,
+            user_agent: defaults.user_agent,
+            auto_pull: defaults.auto_pull,
+            proxy_url: defaults.proxy_url,
+            no_proxy: defaults.no_proxy,
+            tls_root_cert_pem: defaults.tls_root_cert_pem,
+            tls_accept_invalid_certs: defaults.tls_accept_invalid_certs,
+            connect_timeout_seconds: defaults.connect_timeout_seconds,
+            read_timeout_seconds: defaults.read_timeout_seconds,
+            pool_idle_timeout_seconds: defaults.pool_idle_timeout_seconds,
+            pool_max_idle_per_host: defaults.pool_max_idle_per_host,
+            requests_per_second: defaults.requests_per_second,
+            failure_threshold: defaults.failure_threshold,
+            cooldown_ms: defaults.cooldown_ms,
+            cache_capacity: defaults.cache_capacity,
+            cache_ttl_seconds: defaults.cache_ttl_seconds,
+            max_retries: defaults.max_retries,
+            ..config
+        };
+        config
+            .validate()
+            .map_err(|e| reject(StatusCode::BAD_REQUEST, e))?;
+        Ok(config)
+    }
+}
+
+fn reject(status: StatusCode, err: AgentError) -> Rejection {
+    (status, Json(json!({ "error": err.to_string() })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_endpoints_validate_and_return_results() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        // Nothing listens on the backend port, so execution fails fast
+        let allowed = vec!["http://127.0.0.1:9/".to_string()];
+        tokio::spawn(serve(listener, AgentPool::new().unwrap(), allowed));
+
+        let client = reqwest::Client::new();
+        let config = json!({
+            "name": "srv",
+            "model": "m",
+            "ollama_url": "http://127.0.0.1:9",
+            "temperature": 0.5,
+            "timeout_seconds": 5,
+        });
+
+        let response = client
+            .post(format!("{base}/execute"))
+            .json(&json!({ "config": config, "prompt": "hi" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let result: AgentResult = response.json().await.unwrap();
+        assert_eq!(result.agent_id, "srv");
+        assert_eq!(result.status, crate::AgentStatus::Failed);
+
+        let mut invalid = config.clone();
+        invalid["temperature"] = json!(5.0);
+        let response = client
+            .post(format!("{base}/execute_parallel"))
+            .json(&json!({ "requests": [
+                { "config": config, "prompt": "a" },
+                { "config": invalid, "prompt": "b" },
+            ] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert!(body["error"].as_str().unwrap().contains("temperature"));
+
+        let mut elsewhere = config.clone();
+        elsewhere["ollama_url"] = json!("http://169.254.169.254");
+        let response = client
+            .post(format!("{base}/execute"))
+            .json(&json!({ "config": elsewhere, "prompt": "hi" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 403);
+    }
+
+    #[test]
+    fn test_admit_resets_connection_settings() {
+        let gateway = Gateway {
+            pool: AgentPool::new().unwrap(),
+            ollama_urls: Arc::from(["http://localhost:11434".to_string()]),
+        };
+        let config = AgentConfig {
+            name: "srv".to_string(),
+            model: "m".to_string(),
+            headers: [("X-Forwarded-For".to_string(), "x".to_string())].into(),
+            bearer_token: Some("stolen".to_string()),
+            proxy_url: Some("http://proxy:3128".to_string()),
+            auto_pull: true,
+            requests_per_second: Some(1e-9),
+            failure_threshold: 1,
+            cooldown_ms: u64::MAX,
+            cache_capacity: usize::MAX,
+            max_retries: 1000,
+            ..AgentConfig::default()
+        };
+
+        let admitted = gateway.admit(config).unwrap();
+        assert!(admitted.headers.is_empty());
+        assert_eq!(admitted.bearer_token, None);
+        assert_eq!(admitted.proxy_url, None);
+        assert!(!admitted.auto_pull);
+        let defaults = AgentConfig::default();
+        assert_eq!(admitted.requests_per_second, defaults.requests_per_second);
+        assert_eq!(admitted.failure_threshold, defaults.failure_threshold);
+        assert_eq!(admitted.cooldown_ms, defaults.cooldown_ms);
+        assert_eq!(admitted.cache_capacity, defaults.cache_capacity);
+        assert_eq!(admitted.max_retries, defaults.max_retries);
+        assert_eq!(admitted.name, "srv");
+    }
+}
@@ -0,0 +1,94 @@
+// Multi-turn conversations that keep their own chat history
+
+use crate::{
+    agent_runtime::{AgentConfig, AgentResult, AgentStatus, ChatMessage, RustAgent},
+    error::Result,
+};
+
+/// A conversation with one agent over `/api/chat`. Each [`Session::send`]
+/// sends the whole history plus the new message, so the model sees every
+/// earlier turn; the config's `system` prompt is prepended on every request
+/// rather than stored in the history.
+#[derive(Clone)]
+pub struct Session {
+    agent: RustAgent,
+    history: Vec<ChatMessage>,
+}
+
+impl Session {
+    pub fn new(config: AgentConfig) -> Result<Self> {
+        Ok(Self::with_agent(RustAgent::new(config)?))
+    }
+
+    /// A session driven by an existing agent, e.g. one with a custom backend.
+    pub fn with_agent(agent: RustAgent) -> Self {
+        Self {
+            agent,
+            history: Vec::new(),
+        }
+    }
+
+    /// Sends `message` as the next user turn and returns the reply. Only a
+    /// `Completed` exchange is added to the history, so a failed or timed-out
+    /// turn can simply be sent again.
+    pub async fn send(&mut self, message: &str) -> Result<AgentResult> {
+        self.history.push(ChatMessage::new("user", message));
+        let outcome = self.agent.chat(&self.history).await;
+
+        match &outcome {
+            Ok(result) if result.status == AgentStatus::Completed => {
+                let reply = result.output.clone().unwrap_or_default();
+                self.history.push(ChatMessage::new("assistant", reply));
+            },
+            _ => {
+                self.history.pop();
+            },
+        }
+        outcome
+    }
+
+    /// Every completed turn so far, oldest first.
+    pub fn history(&self) -> &[ChatMessage] {
+        &self.history
+    }
+
+    /// Forgets the conversation; the next `send` starts from scratch.
+    pub fn reset(&mut self) {
+        self.history.clear();
+    }
+
+    pub fn agent(&self) -> &RustAgent {
+        &self.agent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::mock::{MockBackend, MockReply};
+
+    #[tokio::test]
+    async fn test_send_accumulates_history_and_rolls_back_failures() {
+        let backend = Arc::new(MockBackend::with_response("hi there"));
+        let mut session = Session::with_agent(RustAgent::with_backend(
+            AgentConfig::default(),
+            backend.clone(),
+        ));
+
+        session.send("hello").await.unwrap();
+        session.send("how are you?").await.unwrap();
+        let second = &backend.requests()[1].body["messages"];
+        assert_eq!(second.as_array().unwrap().len(), 3);
+        assert_eq!(second[1]["content"], "hi there");
+        assert_eq!(session.history().len(), 4);
+
+        backend.push(MockReply::ConnectionError("refused".to_string()));
+        assert!(session.send("still there?").await.is_err());
+        assert_eq!(session.history().len(), 4);
+
+        session.reset();
+        assert!(session.history().is_empty());
+    }
+}
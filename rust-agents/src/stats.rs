@@ -0,0 +1,211 @@
+// Summary statistics over batches of agent results
+
+use crate::agent_runtime::{AgentResult, AgentStatus};
+
+/// Latency distribution of a batch, in milliseconds. All fields are zero for
+/// an empty batch.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatencyStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+impl LatencyStats {
+    pub fn from_durations(durations: &[f64]) -> Self {
+        if durations.is_empty() {
+            return Self::default();
+        }
+
+        let mut sorted = durations.to_vec();
+        sorted.sort_by(f64::total_cmp);
+
+        Self {
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p50: percentile(&sorted, 50.0),
+            p95: percentile(&sorted, 95.0),
+            p99: percentile(&sorted, 99.0),
+        }
+    }
+
+    pub fn from_results(results: &[AgentResult]) -> Self {
+        let durations: Vec<f64> = results.iter().map(|r| r.duration_ms as f64).collect();
+        Self::from_durations(&durations)
+    }
+}
+
+/// Token throughput of a batch. Results without token counts (failures,
+/// backends that don't report usage) contribute only their duration.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ThroughputReport {
+    pub total_prompt_tokens: u64,
+    pub total_completion_tokens: u64,
+    /// Completion tokens per second of wall-clock time
+    pub tokens_per_second: f64,
+    pub wall_clock_ms: f64,
+    /// Sum of every result's `duration_ms`
+    pub summed_duration_ms: f64,
+    /// `summed_duration_ms / wall_clock_ms`: roughly how many agents were
+    /// running at once on average
+    pub parallelism: f64,
+}
+
+impl ThroughputReport {
+    /// `wall_clock_ms` is the elapsed time of the whole batch. Without it,
+    /// the longest single duration is used, which assumes every agent
+    /// started at the same moment.
+    pub fn from_results(results: &[AgentResult], wall_clock_ms: Option<f64>) -> Self {
+        Self::from_usage(
+            results
+                .iter()
+                .map(|r| (r.prompt_tokens, r.completion_tokens, r.duration_ms as f64)),
+            wall_clock_ms,
+        )
+    }
+
+    // Shared with the Python bindings: `(prompt_tokens, completion_tokens,
+    // duration_ms)` per result
+    pub(crate) fn from_usage(
+        usage: impl IntoIterator<Item = (Option<u64>, Option<u64>, f64)>,
+        wall_clock_ms: Option<f64>,
+    ) -> Self {
+        let mut report = Self::default();
+        let mut longest: f64 = 0.0;
+        for (prompt, completion, duration_ms) in usage {
+            report.total_prompt_tokens += prompt.unwrap_or(0);
+            report.total_completion_tokens += completion.unwrap_or(0);
+            report.summed_duration_ms += duration_ms;
+            longest = longest.max(duration_ms);
+        }
+
+        report.wall_clock_ms = wall_clock_ms.unwrap_or(longest);
+        if report.wall_clock_ms > 0.0 {
+            report.tokens_per_second =
+                report.total_completion_tokens as f64 / (report.wall_clock_ms / 1000.0);
+            report.parallelism = report.summed_duration_ms / report.wall_clock_ms;
+        }
+        report
+    }
+
+    /// Rough cost given per-1000-token prices for prompt and completion
+    /// tokens.
+    pub fn estimated_cost(&self, prompt_per_1k: f64, completion_per_1k: f64) -> f64 {
+        (self.total_prompt_tokens as f64 * prompt_per_1k
+            + self.total_completion_tokens as f64 * completion_per_1k)
+            / 1000.0
+    }
+}
+
+/// A batch split by [`AgentStatus`], each bucket keeping the input order.
+#[derive(Debug, Clone, Default)]
+pub struct ResultPartition {
+    pub completed: Vec<AgentResult>,
+    pub failed: Vec<AgentResult>,
+    pub timed_out: Vec<AgentResult>,
+    pub cancelled: Vec<AgentResult>,
+}
+
+/// Sorts `results` into one bucket per status.
+pub fn partition_results(results: Vec<AgentResult>) -> ResultPartition {
+    let mut partition = ResultPartition::default();
+    for result in results {
+        match result.status {
+            AgentStatus::Completed => partition.completed.push(result),
+            AgentStatus::Failed => partition.failed.push(result),
+            AgentStatus::TimedOut => partition.timed_out.push(result),
+            AgentStatus::Cancelled => partition.cancelled.push(result),
+        }
+    }
+    partition
+}
+
+/// Orders `results` by `duration_ms`, slowest first when `descending`, for
+/// finding the agents that held a batch up. Ties keep their relative order.
+pub fn sort_by_duration(results: &mut [AgentResult], descending: bool) {
+    if descending {
+        results.sort_by_key(|result| std::cmp::Reverse(result.duration_ms));
+    } else {
+        results.sort_by_key(|result| result.duration_ms);
+    }
+}
+
+/// Nearest-rank percentile of an ascending slice; `p` is in `0.0..=100.0`.
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_stats_edge_cases() {
+        assert_eq!(LatencyStats::from_durations(&[]), LatencyStats::default());
+
+        let single = LatencyStats::from_durations(&[42.0]);
+        assert_eq!(
+            (single.min, single.p50, single.p99, single.max),
+            (42.0, 42.0, 42.0, 42.0)
+        );
+
+        let durations: Vec<f64> = (1..=100).map(f64::from).collect();
+        let stats = LatencyStats::from_durations(&durations);
+        assert_eq!(stats.p50, 50.0);
+        assert_eq!(stats.p95, 95.0);
+        assert_eq!(stats.p99, 99.0);
+        assert_eq!(stats.max, 100.0);
+    }
+
+    #[test]
+    fn test_throughput_report() {
+        let report = ThroughputReport::from_usage(
+            vec![
+                (Some(10), Some(100), 1000.0),
+                (Some(20), Some(300), 2000.0),
+                (None, None, 500.0),
+            ],
+            None,
+        );
+        assert_eq!(report.total_prompt_tokens, 30);
+        assert_eq!(report.total_completion_tokens, 400);
+        assert_eq!(report.wall_clock_ms, 2000.0);
+        assert_eq!(report.tokens_per_second, 200.0);
+        assert_eq!(report.parallelism, 1.75);
+        assert_eq!(report.estimated_cost(1.0, 2.0), 0.83);
+    }
+
+    #[test]
+    fn test_sort_by_duration() {
+        let mut results: Vec<AgentResult> = [("a", 30), ("b", 10), ("c", 30), ("d", 20)]
+            .into_iter()
+            .map(|(id, duration_ms)| {
+                serde_json::from_value(serde_json::json!({
+                    "agent_id": id,
+                    "run_id": uuid::Uuid::nil(),
+                    "status": "completed",
+                    "output": null,
+                    "error": null,
+                    "duration_ms": duration_ms,
+                }))
+                .unwrap()
+            })
+            .collect();
+        let ids = |results: &[AgentResult]| -> String {
+            results.iter().map(|r| r.agent_id.as_str()).collect()
+        };
+
+        sort_by_duration(&mut results, true);
+        assert_eq!(ids(&results), "acdb");
+        sort_by_duration(&mut results, false);
+        assert_eq!(ids(&results), "bdac");
+    }
+}
@@ -0,0 +1,179 @@
+// Incremental decoding of Ollama's newline-delimited JSON streaming responses
+
+use std::{
+    pin::Pin,
+    time::{Duration, Instant},
+};
+
+use futures::{Stream, StreamExt};
+
+use crate::error::{AgentError, Result};
+
+/// Throughput so far of a stream from
+/// [`crate::RustAgent::execute_stream_with_progress`]. Tokens are counted as
+/// chunks, which Ollama sends one token at a time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamProgress {
+    pub tokens_so_far: u64,
+    /// Time since the server accepted the request
+    pub elapsed_ms: u64,
+    pub tokens_per_sec: f64,
+}
+
+impl StreamProgress {
+    pub(crate) fn new(tokens_so_far: u64, elapsed: Duration) -> Self {
+        let seconds = elapsed.as_secs_f64();
+        Self {
+            tokens_so_far,
+            elapsed_ms: u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX),
+            tokens_per_sec: if seconds > 0.0 {
+                tokens_so_far as f64 / seconds
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+/// An item of [`crate::RustAgent::execute_stream_with_progress`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    Chunk(String),
+    Progress(StreamProgress),
+}
+
+/// Interleaves `chunks` with a [`StreamProgress`] after the first chunk to
+/// arrive at least `interval` after the previous report.
+pub(crate) fn with_progress(
+    chunks: impl Stream<Item = Result<String>>,
+    interval: Duration,
+) -> impl Stream<Item = Result<StreamEvent>> {
+    let start = Instant::now();
+    let mut last_report = start;
+    let mut tokens = 0;
+
+    chunks.flat_map(move |chunk| {
+        let mut events = Vec::with_capacity(2);
+        match chunk {
+            Ok(text) => {
+                tokens += 1;
+                events.push(Ok(StreamEvent::Chunk(text)));
+                if last_report.elapsed() >= interval {
+                    last_report = Instant::now();
+                    events.push(Ok(StreamEvent::Progress(StreamProgress::new(
+                        tokens,
+                        start.elapsed(),
+                    ))));
+                }
+            },
+            Err(e) => events.push(Err(e)),
+        }
+        futures::stream::iter(events)
+    })
+}
+
+struct NdjsonState<S> {
+    body: Pin<Box<S>>,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+/// Turns a raw byte stream of NDJSON lines into a stream of `response` text
+/// chunks. The stream ends after the line carrying `"done": true`, or when the
+/// body is exhausted.
+pub(crate) fn response_chunks<S, B, E>(body: S) -> impl Stream<Item = Result<String>>
+where
+    S: Stream<Item = std::result::Result<B, E>>,
+    B: AsRef<[u8]>,
+    E: Into<AgentError>,
+{
+    let state = NdjsonState {
+        body: Box::pin(body),
+        buffer: Vec::new(),
+        done: false,
+    };
+
+    futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if state.done {
+                return None;
+            }
+
+            if let Some(pos) = state.buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = state.buffer.drain(..=pos).collect();
+                match parse_line(&line) {
+                    Ok(None) => continue,
+                    Ok(Some((chunk, done))) => {
+                        state.done = done;
+                        if done && chunk.is_empty() {
+                            return None;
+                        }
+                        return Some((Ok(chunk), state));
+                    },
+                    Err(e) => {
+                        state.done = true;
+                        return Some((Err(e), state));
+                    },
+                }
+            }
+
+            match state.body.next().await {
+                Some(Ok(bytes)) => state.buffer.extend_from_slice(bytes.as_ref()),
+                Some(Err(e)) => {
+                    state.done = true;
+                    return Some((Err(e.into()), state));
+                },
+                None => {
+                    // Flush a trailing line that wasn't newline-terminated
+                    state.done = true;
+                    let line = std::mem::take(&mut state.buffer);
+                    return match parse_line(&line) {
+                        Ok(Some((chunk, _))) if !chunk.is_empty() => Some((Ok(chunk), state)),
+                        Ok(_) => None,
+                        Err(e) => Some((Err(e), state)),
+                    };
+                },
+            }
+        }
+    })
+}
+
+// Returns `None` for blank lines, otherwise the chunk text and the `done` flag
+fn parse_line(line: &[u8]) -> Result<Option<(String, bool)>> {
+    let line = line.trim_ascii();
+    if line.is_empty() {
+        return Ok(None);
+    }
+
+    let value: serde_json::Value = serde_json::from_slice(line)?;
+    if let Some(error) = value.get("error").and_then(|v| v.as_str()) {
+        return Err(AgentError::Backend(format!("ollama stream error: {error}")));
+    }
+
+    let chunk = value
+        .get("response")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let done = value.get("done").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    Ok(Some((chunk, done)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_response_chunks_split_across_reads() {
+        let body = futures::stream::iter(vec![
+            Ok::<_, AgentError>(b"{\"response\":\"Hel\",\"done\":false}\n{\"resp".to_vec()),
+            Ok(b"onse\":\"lo\",\"done\":false}\n".to_vec()),
+            Ok(b"{\"response\":\"\",\"done\":true}\n".to_vec()),
+        ]);
+
+        let chunks: Vec<String> = response_chunks(body).map(|c| c.unwrap()).collect().await;
+
+        assert_eq!(chunks, vec!["Hel", "lo"]);
+    }
+}
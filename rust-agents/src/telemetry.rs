@@ -0,0 +1,33 @@
+// Optional `tracing` instrumentation. Everything here compiles to nothing
+// unless the `tracing` feature is enabled, so call sites need no cfg gates.
+
+use std::future::Future;
+
+/// Emits a `tracing` event at the given level when the feature is enabled.
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+    }};
+}
+
+pub(crate) use trace_event;
+
+/// Carries the caller's span into a spawned task so per-agent spans nest
+/// under the batch span.
+pub(crate) fn in_current_span<F: Future>(fut: F) -> impl Future<Output = F::Output> {
+    #[cfg(feature = "tracing")]
+    {
+        tracing::Instrument::in_current_span(fut)
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        fut
+    }
+}
+
+/// Records the elapsed time on the current span's `duration_ms` field.
+pub(crate) fn record_duration(_duration_ms: u128) {
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("duration_ms", _duration_ms as u64);
+}
@@ -0,0 +1,130 @@
+// Prompt templates with `{var}` placeholders
+
+use std::collections::HashMap;
+
+use crate::error::{AgentError, Result};
+
+/// A prompt skeleton with `{name}` placeholders, where `name` is made of
+/// letters, digits, and underscores. `{{` and `}}` render as literal braces;
+/// any other brace (e.g. in an embedded JSON example) is left as-is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTemplate {
+    template: String,
+}
+
+impl PromptTemplate {
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// The distinct placeholder names, in order of first appearance.
+    pub fn placeholders(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        let mut rest = self.template.as_str();
+
+        while let Some(pos) = rest.find(['{', '}']) {
+            rest = &rest[pos..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                rest = &rest[2..];
+                continue;
+            }
+            match placeholder(rest) {
+                Some(name) => {
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                    rest = &rest[name.len() + 2..];
+                },
+                None => rest = &rest[1..],
+            }
+        }
+        names
+    }
+
+    /// Substitutes every placeholder from `vars`. Fails listing the
+    /// placeholders that have no value; unused `vars` are ignored.
+    pub fn render(&self, vars: &HashMap<String, String>) -> Result<String> {
+        let mut out = String::with_capacity(self.template.len());
+        let mut missing: Vec<&str> = Vec::new();
+        let mut rest = self.template.as_str();
+
+        while let Some(pos) = rest.find(['{', '}']) {
+            out.push_str(&rest[..pos]);
+            rest = &rest[pos..];
+
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                out.push_str(&rest[..1]);
+                rest = &rest[2..];
+                continue;
+            }
+
+            match placeholder(rest) {
+                Some(name) => {
+                    match vars.get(name) {
+                        Some(value) => out.push_str(value),
+                        None if !missing.contains(&name) => missing.push(name),
+                        None => {},
+                    }
+                    rest = &rest[name.len() + 2..];
+                },
+                None => {
+                    out.push_str(&rest[..1]);
+                    rest = &rest[1..];
+                },
+            }
+        }
+        out.push_str(rest);
+
+        if !missing.is_empty() {
+            return Err(AgentError::Config(format!(
+                "unresolved template placeholders: {}",
+                missing.join(", ")
+            )));
+        }
+        Ok(out)
+    }
+}
+
+// The name in a `{name}` at the start of `s`, if it is a placeholder
+fn placeholder(s: &str) -> Option<&str> {
+    let body = s.strip_prefix('{')?;
+    let end = body.find('}')?;
+    let name = &body[..end];
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_substitutes_escapes_and_reports_missing() {
+        let template =
+            PromptTemplate::new(r#"Write {lang} for {task}. Reply as {"code": ...} {{literal}}"#);
+        let vars = HashMap::from([
+            ("lang".to_string(), "Rust".to_string()),
+            ("task".to_string(), "parsing".to_string()),
+        ]);
+        assert_eq!(
+            template.render(&vars).unwrap(),
+            r#"Write Rust for parsing. Reply as {"code": ...} {literal}"#
+        );
+
+        assert_eq!(template.placeholders(), ["lang", "task"]);
+
+        let err = PromptTemplate::new("{a} {b} {a}")
+            .render(&HashMap::new())
+            .unwrap_err();
+        assert_eq!(
+            err,
+            AgentError::Config("unresolved template placeholders: a, b".to_string())
+        );
+    }
+}
@@ -0,0 +1,32 @@
+// Rough token counts for checking prompts against context windows offline
+
+const CHARS_PER_TOKEN: usize = 4;
+
+/// An estimate of how many tokens `text` encodes to: one per four characters,
+/// rounded up, which is close for English prose under common BPE vocabularies
+/// and tends to undercount code and non-Latin scripts. It is only an estimate,
+/// never a replacement for the model's tokenizer, but it never decreases as
+/// text is appended, so it suits pre-flight checks against `num_ctx` and
+/// deciding where to split.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_grows_with_text() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("a"), 1);
+        assert_eq!(estimate_tokens("four"), 1);
+        assert_eq!(estimate_tokens("héllo"), 2, "counts chars, not bytes");
+
+        let text = "The quick brown fox jumps over the lazy dog.";
+        let estimates: Vec<usize> = (0..=text.len())
+            .map(|n| estimate_tokens(&text[..n]))
+            .collect();
+        assert!(estimates.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
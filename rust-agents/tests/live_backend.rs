@@ -0,0 +1,26 @@
+// Tests against a real Ollama server; run with `--features live-tests`.
+// OLLAMA_URL and OLLAMA_MODEL pick the server and model.
+#![cfg(feature = "live-tests")]
+
+use agent_runtime::{AgentConfig, AgentStatus, RustAgent};
+
+fn live_agent() -> RustAgent {
+    let mut config = AgentConfig {
+        name: "live".to_string(),
+        model: "qwen2.5-coder:14b".to_string(),
+        ..Default::default()
+    };
+    config.apply_env_overrides().unwrap();
+    RustAgent::new(config).unwrap()
+}
+
+#[tokio::test]
+async fn test_reproducible_runs_are_byte_identical() {
+    let agent = live_agent();
+    let task = "Write one sentence about rivers.";
+
+    let first = agent.execute_reproducible(task, 1234).await.unwrap();
+    let second = agent.execute_reproducible(task, 1234).await.unwrap();
+    assert_eq!(first.status, AgentStatus::Completed, "{:?}", first.error);
+    assert_eq!(first.output, second.output);
+}